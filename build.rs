@@ -0,0 +1,88 @@
+#![allow(clippy::needless_return)]
+
+//! Generates the table of vendors embedded in the binary, sorted by prefix.
+//! The full IEEE MA-L registry is used when it is found, from the file given
+//! in the ARPLAYER_OUI_REGISTRY variable or the ones installed by the
+//! ieee-data and hwdata packages, on top of the prefixes of data/oui.txt.
+//! That file can also be refreshed from the registry with
+//! scripts/update-oui.sh.
+
+#[path = "src/oui/line.rs"]
+mod line;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+const OUI_FILE: &str = "data/oui.txt";
+
+const REGISTRY_VAR: &str = "ARPLAYER_OUI_REGISTRY";
+
+/// Copies of the IEEE registry installed by the distributions.
+const SYSTEM_REGISTRIES: [&str; 3] = [
+    "/usr/share/ieee-data/oui.txt",
+    "/usr/share/hwdata/oui.txt",
+    "/usr/share/misc/oui.txt",
+];
+
+/// Below this number of prefixes the table is not the full registry, which
+/// has tens of thousands.
+const FULL_REGISTRY_MIN: usize = 10000;
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", OUI_FILE);
+    println!("cargo:rerun-if-changed=src/oui/line.rs");
+    println!("cargo:rerun-if-env-changed={}", REGISTRY_VAR);
+
+    // the last line of a repeated prefix wins, as when loading a file
+    let mut vendors = BTreeMap::new();
+    load(OUI_FILE, &mut vendors);
+
+    let registry = match env::var(REGISTRY_VAR) {
+        Ok(path) => Some(path),
+        Err(_) => SYSTEM_REGISTRIES
+            .iter()
+            .find(|path| Path::new(path).is_file())
+            .map(|path| path.to_string()),
+    };
+    if let Some(path) = &registry {
+        println!("cargo:rerun-if-changed={}", path);
+        load(path, &mut vendors);
+    }
+
+    if vendors.len() < FULL_REGISTRY_MIN {
+        println!(
+            "cargo:warning=Only {} OUI prefixes embedded, set {} to the IEEE oui.txt or run scripts/update-oui.sh to embed the full registry",
+            vendors.len(),
+            REGISTRY_VAR
+        );
+    }
+
+    let mut table = String::from("&[\n");
+    for (oui, vendor) in vendors.iter() {
+        writeln!(
+            table,
+            "    ([0x{:02x}, 0x{:02x}, 0x{:02x}], {:?}),",
+            oui[0], oui[1], oui[2], vendor
+        )
+        .unwrap();
+    }
+    table.push_str("]\n");
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("oui_table.rs"), table)
+        .expect("Error writing the OUI table");
+}
+
+fn load(path: &str, vendors: &mut BTreeMap<[u8; 3], String>) {
+    // the IEEE registry is not always valid UTF-8
+    let content = fs::read(path)
+        .unwrap_or_else(|e| panic!("Error reading {}: {}", path, e));
+    for line in String::from_utf8_lossy(&content).lines() {
+        if let Some((oui, vendor)) = line::parse_oui_line(line) {
+            vendors.insert(oui, vendor);
+        }
+    }
+}
@@ -0,0 +1,24 @@
+#!/bin/sh
+# Refreshes data/oui.txt with the IEEE MA-L registry (the 24-bit OUIs). The
+# vendors are embedded at build time, so rebuild arplayer afterwards.
+set -e
+
+URL="${OUI_URL:-https://standards-oui.ieee.org/oui/oui.txt}"
+OUT="$(dirname "$0")/../data/oui.txt"
+TMP="$OUT.tmp"
+
+{
+    echo "# OUI vendor prefixes embedded in arplayer, generated from"
+    echo "# $URL by scripts/update-oui.sh."
+    echo "#"
+    echo "# Each line contains a 24-bit prefix followed by the vendor name. The IEEE"
+    echo "# oui.txt format (\"00-00-0C   (hex)   Cisco Systems, Inc\") is also accepted,"
+    echo "# so a newer copy of the registry can be passed with --oui-file."
+    curl -fsSL "$URL" \
+        | tr -d '\r' \
+        | sed -nE 's/^([0-9A-Fa-f]{2})-([0-9A-Fa-f]{2})-([0-9A-Fa-f]{2})[[:space:]]+\(hex\)[[:space:]]+(.*[^[:space:]])[[:space:]]*$/\1:\2:\3\t\4/p' \
+        | sort -u -k1,1
+} > "$TMP"
+
+mv "$TMP" "$OUT"
+echo "$(grep -vc '^#' "$OUT") prefixes written to $OUT"
@@ -0,0 +1,100 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+use pnet::datalink::NetworkInterface;
+use std::time::Duration;
+
+use super::{filter, helpers, oui};
+use super::scan::{parse_target, Target};
+
+pub const COMMAND_NAME: &str = "conflict";
+
+pub fn command() -> App<'static, 'static> {
+    SubCommand::with_name(COMMAND_NAME)
+        .about("Detect IP address conflicts with ARP probes (RFC 5227)")
+        .arg(
+            Arg::with_name("iface")
+                .long("iface")
+                .short("I")
+                .required(true)
+                .takes_value(true)
+                .validator(helpers::is_interface)
+                .help("Interface to send the probes"),
+        )
+        .arg(
+            Arg::with_name("ip")
+                .takes_value(true)
+                .validator(helpers::is_ip_or_net)
+                .help("Ip or Network to probe, if none, interface network will be probed"),
+        )
+        .arg(
+            Arg::with_name("probes")
+                .long("probes")
+                .short("p")
+                .takes_value(true)
+                .default_value("3")
+                .validator(helpers::is_u32)
+                .help("Number of probes sent to each IP"),
+        )
+        .arg(
+            Arg::with_name("rate")
+                .long("rate")
+                .short("r")
+                .takes_value(true)
+                .default_value("1000")
+                .value_name("pps")
+                .validator(helpers::is_u64)
+                .help("Max number of ARP probes sent per second. If 0, there is no limit"),
+        )
+        .arg(
+            Arg::with_name("wait")
+                .long("wait")
+                .short("w")
+                .takes_value(true)
+                .default_value("1000")
+                .value_name("millis")
+                .validator(helpers::is_u64)
+                .help("Time to wait for replies after the last probe"),
+        )
+        .arg(filter::arg())
+        .arg(oui::arg())
+        .arg(
+            Arg::with_name("verbosity")
+                .short("v")
+                .multiple(true)
+                .help("Increase message verbosity"),
+        )
+}
+
+pub struct Arguments {
+    pub iface: NetworkInterface,
+    pub target: Option<Target>,
+    pub probes: u32,
+    pub rate: Option<u64>,
+    pub wait: Duration,
+    pub filter: Option<String>,
+    pub oui_file: Option<String>,
+    pub verbosity: usize,
+}
+
+impl<'a> Arguments {
+    pub fn parse(matches: &'a ArgMatches) -> Arguments {
+        let iface =
+            helpers::lookup_interface(matches.value_of("iface").unwrap())
+                .unwrap();
+
+        Self {
+            iface,
+            target: matches.value_of("ip").map(parse_target),
+            probes: matches.value_of("probes").unwrap().parse().unwrap(),
+            rate: match matches.value_of("rate").unwrap().parse().unwrap() {
+                0 => None,
+                rate => Some(rate),
+            },
+            wait: Duration::from_millis(
+                matches.value_of("wait").unwrap().parse().unwrap(),
+            ),
+            filter: filter::parse(matches),
+            oui_file: oui::parse(matches),
+            verbosity: matches.occurrences_of("verbosity") as usize,
+        }
+    }
+}
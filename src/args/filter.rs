@@ -0,0 +1,22 @@
+use crate::validators;
+use clap::{Arg, ArgMatches};
+
+/// Argument with the expression to select the ARP packets, shared by the
+/// commands that listen.
+pub fn arg() -> Arg<'static, 'static> {
+    return Arg::with_name("filter")
+        .long("filter")
+        .takes_value(true)
+        .value_name("expr")
+        .validator(is_filter)
+        .help("Only handle the ARP packets that match the expression: src/dst ip <ip|net|first-last>, src/dst mac <mac|prefix:*>, ether src/dst <mac|prefix:*>, opcode <request|reply|number>, request, reply, broadcast, mismatch, gratuitous, combined with and/or/not and parentheses");
+}
+
+pub fn parse(matches: &ArgMatches) -> Option<String> {
+    return matches.value_of("filter").map(|f| f.to_string());
+}
+
+fn is_filter(v: String) -> Result<(), String> {
+    validators::parse_filter(&v)?;
+    return Ok(());
+}
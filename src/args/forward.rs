@@ -1,9 +1,34 @@
 use clap::{App, Arg, ArgMatches, SubCommand};
+use pnet::datalink::NetworkInterface;
+
+use super::helpers;
 
 pub const COMMAND_NAME: &str = "forward";
 
 pub fn command() -> App<'static, 'static> {
-    SubCommand::with_name(COMMAND_NAME).about("Enable/Disable IP forwarding")
+    SubCommand::with_name(COMMAND_NAME).about("Manage IP forwarding and related sysctls")
+        .arg(
+            Arg::with_name("iface")
+                .long("iface")
+                .short("I")
+                .takes_value(true)
+                .validator(helpers::is_interface)
+                .help("Interface to include its sysctls"),
+        )
+        .arg(
+            Arg::with_name("ipv4")
+                .short("4")
+                .long("ipv4")
+                .conflicts_with_all(&["mitm", "restore"])
+                .help("Select IPv4 forwarding (default for enable/disable)"),
+        )
+        .arg(
+            Arg::with_name("ipv6")
+                .short("6")
+                .long("ipv6")
+                .conflicts_with_all(&["mitm", "restore"])
+                .help("Select IPv6 forwarding"),
+        )
         .arg(
             Arg::with_name("enable")
                 .short("e")
@@ -17,6 +42,28 @@ pub fn command() -> App<'static, 'static> {
                 .help("Disable IP forwarding")
                 .conflicts_with("enable"),
         )
+        .arg(
+            Arg::with_name("mitm")
+                .long("mitm")
+                .requires("iface")
+                .conflicts_with_all(&["enable", "disable"])
+                .help("Apply the MitM profile: enable IP forwarding and disable send_redirects, rp_filter and proxy_arp"),
+        )
+        .arg(
+            Arg::with_name("save")
+                .long("save")
+                .takes_value(true)
+                .value_name("file")
+                .help("Save the current sysctl values to a file, before applying any change"),
+        )
+        .arg(
+            Arg::with_name("restore")
+                .long("restore")
+                .takes_value(true)
+                .value_name("file")
+                .conflicts_with_all(&["enable", "disable", "mitm", "save"])
+                .help("Restore the sysctl values saved in a file"),
+        )
         .arg(
             Arg::with_name("verbosity")
                 .short("v")
@@ -25,14 +72,24 @@ pub fn command() -> App<'static, 'static> {
         )
 }
 
+#[derive(Debug, Clone, Copy)]
+pub enum Family {
+    Ipv4,
+    Ipv6,
+}
+
 pub struct Arguments {
+    pub iface: Option<NetworkInterface>,
+    pub families: Vec<Family>,
     pub enable: Option<bool>,
+    pub mitm: bool,
+    pub save: Option<String>,
+    pub restore: Option<String>,
     pub verbosity: usize,
 }
 
 impl<'a> Arguments {
     pub fn parse(matches: &'a ArgMatches) -> Arguments {
-
         let enable = if matches.is_present("enable") {
             Some(true)
         } else if matches.is_present("disable") {
@@ -41,8 +98,32 @@ impl<'a> Arguments {
             None
         };
 
+        // Without selectors, only IPv4 is changed, since enabling IPv6
+        // forwarding makes the kernel ignore router advertisements, but all
+        // the families are shown.
+        let mut families = Vec::new();
+        if matches.is_present("ipv4") {
+            families.push(Family::Ipv4);
+        }
+        if matches.is_present("ipv6") {
+            families.push(Family::Ipv6);
+        }
+        if families.is_empty() {
+            families.push(Family::Ipv4);
+            if enable.is_none() {
+                families.push(Family::Ipv6);
+            }
+        }
+
         Self {
+            iface: matches
+                .value_of("iface")
+                .map(|iface| helpers::lookup_interface(iface).unwrap()),
+            families,
             enable,
+            mitm: matches.is_present("mitm"),
+            save: matches.value_of("save").map(|f| f.to_string()),
+            restore: matches.value_of("restore").map(|f| f.to_string()),
             verbosity: matches.occurrences_of("verbosity") as usize,
         }
     }
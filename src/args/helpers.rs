@@ -1,3 +1,4 @@
+use crate::validators;
 use pnet::datalink::{self, NetworkInterface};
 use pnet::ipnetwork::Ipv4Network;
 use pnet::util::MacAddr;
@@ -14,6 +15,40 @@ pub fn is_u64(v: String) -> Result<(), String> {
     return Ok(());
 }
 
+pub fn is_positive_u64(v: String) -> Result<(), String> {
+    let n = v.parse::<u64>().map_err(|_| {
+        format!(
+            "Incorrect value '{}' must be an unsigned integer of 64 bits (u64)",
+            v
+        )
+    })?;
+    if n == 0 {
+        return Err(format!("Incorrect value '{}' must be greater than 0", v));
+    }
+
+    return Ok(());
+}
+
+pub fn is_u32(v: String) -> Result<(), String> {
+    v.parse::<u32>().map_err(|_| {
+        format!(
+            "Incorrect value '{}' must be an unsigned integer of 32 bits (u32)",
+            v
+        )
+    })?;
+
+    return Ok(());
+}
+
+pub fn is_positive_u32(v: String) -> Result<(), String> {
+    is_u32(v.clone())?;
+    if v.parse::<u32>().unwrap() == 0 {
+        return Err(format!("Incorrect value '{}' must be greater than 0", v));
+    }
+
+    return Ok(());
+}
+
 pub fn is_mac(v: String) -> Result<(), String> {
     match MacAddr::from_str(&v) {
         Ok(_) => Ok(()),
@@ -47,6 +82,19 @@ pub fn is_ip_or_net(v: String) -> Result<(), String> {
     return Err(format!("'{}' is not a valid IPv4 nor range", v));
 }
 
+pub fn is_ip_net_or_range(v: String) -> Result<(), String> {
+    if is_ip_or_net(v.clone()).is_ok()
+        || validators::parse_ip_range(&v).is_some()
+    {
+        return Ok(());
+    }
+
+    return Err(format!(
+        "'{}' is not a valid IPv4, network nor range (first-last)",
+        v
+    ));
+}
+
 pub fn is_ip(v: String) -> Result<(), String> {
     v.parse::<Ipv4Addr>()
         .map_err(|_| format!("'{}' is not a valid IPv4", v))?;
@@ -56,5 +104,5 @@ pub fn is_ip(v: String) -> Result<(), String> {
 pub fn lookup_interface(iface_name: &str) -> Option<NetworkInterface> {
     return datalink::interfaces()
         .into_iter()
-        .find(|iface| &iface.name == iface_name);
+        .find(|iface| iface.name == iface_name);
 }
@@ -1,6 +1,13 @@
+mod filter;
 mod helpers;
+mod oui;
+pub mod conflict;
+mod pcap;
+pub mod ping;
+pub mod recover;
 pub mod reply;
 pub mod scan;
+pub mod sniff;
 pub mod spoof;
 pub mod forward;
 
@@ -12,14 +19,22 @@ fn args() -> App<'static, 'static> {
         .version(env!("CARGO_PKG_VERSION"))
         .setting(AppSettings::SubcommandRequired)
         .subcommand(scan::command())
+        .subcommand(conflict::command())
+        .subcommand(ping::command())
         .subcommand(reply::command())
+        .subcommand(sniff::command())
         .subcommand(spoof::command())
+        .subcommand(recover::command())
         .subcommand(forward::command())
 }
 
 pub enum Arguments {
+    Conflict(conflict::Arguments),
+    Ping(ping::Arguments),
+    Recover(recover::Arguments),
     Reply(reply::Arguments),
     Scan(scan::Arguments),
+    Sniff(sniff::Arguments),
     Spoof(spoof::Arguments),
     Forward(forward::Arguments),
 }
@@ -29,12 +44,30 @@ impl Arguments {
         let matches = args().get_matches();
 
         match matches.subcommand_name().unwrap() {
+            name @ conflict::COMMAND_NAME => {
+                return Arguments::Conflict(conflict::Arguments::parse(
+                    matches.subcommand_matches(name).unwrap(),
+                ))
+            }
+
             name @ forward::COMMAND_NAME => {
                 return Arguments::Forward(forward::Arguments::parse(
                     matches.subcommand_matches(name).unwrap(),
                 ))
             }
 
+            name @ ping::COMMAND_NAME => {
+                return Arguments::Ping(ping::Arguments::parse(
+                    matches.subcommand_matches(name).unwrap(),
+                ))
+            }
+
+            name @ recover::COMMAND_NAME => {
+                return Arguments::Recover(recover::Arguments::parse(
+                    matches.subcommand_matches(name).unwrap(),
+                ))
+            }
+
             name @ reply::COMMAND_NAME => {
                 return Arguments::Reply(reply::Arguments::parse(
                     matches.subcommand_matches(name).unwrap(),
@@ -47,6 +80,12 @@ impl Arguments {
                 ))
             }
 
+            name @ sniff::COMMAND_NAME => {
+                return Arguments::Sniff(sniff::Arguments::parse(
+                    matches.subcommand_matches(name).unwrap(),
+                ))
+            }
+
             name @ spoof::COMMAND_NAME => {
                 return Arguments::Spoof(spoof::Arguments::parse(
                     matches.subcommand_matches(name).unwrap(),
@@ -0,0 +1,15 @@
+use clap::{Arg, ArgMatches};
+
+/// Argument with the file of OUI vendor prefixes, shared by the commands that
+/// show vendors.
+pub fn arg() -> Arg<'static, 'static> {
+    return Arg::with_name("oui-file")
+        .long("oui-file")
+        .takes_value(true)
+        .value_name("file")
+        .help("File with OUI vendor prefixes that override the embedded ones");
+}
+
+pub fn parse(matches: &ArgMatches) -> Option<String> {
+    return matches.value_of("oui-file").map(|f| f.to_string());
+}
@@ -0,0 +1,72 @@
+use crate::pcap::{Filter, PcapOptions};
+use clap::{Arg, ArgMatches};
+use std::time::Duration;
+
+use super::helpers;
+
+/// Arguments to capture the traffic to a pcap file, shared by the commands
+/// that intercept traffic.
+pub fn args() -> Vec<Arg<'static, 'static>> {
+    return vec![
+        Arg::with_name("pcap")
+            .long("pcap")
+            .takes_value(true)
+            .value_name("file")
+            .help("Write the intercepted frames, as received (the ones sent or relayed by us are not written), to a file in the classic pcap format (pcapng is not supported)"),
+        Arg::with_name("pcap-rotate-size")
+            .long("pcap-rotate-size")
+            .takes_value(true)
+            .value_name("MB")
+            .validator(is_rotate_size)
+            .requires("pcap")
+            .help("Continue the capture in a new file (file.1, file.2...) when the current one reaches the given megabytes"),
+        Arg::with_name("pcap-rotate-time")
+            .long("pcap-rotate-time")
+            .takes_value(true)
+            .value_name("seconds")
+            .validator(helpers::is_u64)
+            .requires("pcap")
+            .help("Continue the capture in a new file (file.1, file.2...) every given seconds"),
+        Arg::with_name("pcap-filter")
+            .long("pcap-filter")
+            .takes_value(true)
+            .value_name("filter")
+            .validator(is_filter)
+            .requires("pcap")
+            .help("Only write the frames that match the filter, with a subset of the BPF syntax: [src|dst] host/net/port, ether [src|dst] host, arp, ip, ip6, tcp, udp, icmp (optionally followed by a host/net/port primitive, like tcp port 80), combined with and/or (same precedence, grouped from left to right), not and parentheses"),
+    ];
+}
+
+pub fn parse(matches: &ArgMatches) -> Option<PcapOptions> {
+    let file = matches.value_of("pcap")?.to_string();
+
+    return Some(PcapOptions {
+        file,
+        rotate_size: matches
+            .value_of("pcap-rotate-size")
+            .map(|mb| megabytes_to_bytes(mb).unwrap()),
+        rotate_time: matches
+            .value_of("pcap-rotate-time")
+            .map(|s| Duration::from_secs(s.parse().unwrap())),
+        filter: matches
+            .value_of("pcap-filter")
+            .map(|f| Filter::parse(f).unwrap()),
+    });
+}
+
+fn megabytes_to_bytes(mb: &str) -> Option<u64> {
+    return mb.parse::<u64>().ok()?.checked_mul(1024 * 1024);
+}
+
+fn is_rotate_size(v: String) -> Result<(), String> {
+    helpers::is_u64(v.clone())?;
+    if megabytes_to_bytes(&v).is_none() {
+        return Err(format!("'{}' megabytes is too big", v));
+    }
+    return Ok(());
+}
+
+fn is_filter(v: String) -> Result<(), String> {
+    Filter::parse(&v)?;
+    return Ok(());
+}
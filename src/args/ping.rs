@@ -0,0 +1,84 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+use pnet::datalink::NetworkInterface;
+use std::{net::Ipv4Addr, time::Duration};
+
+use super::{filter, helpers, oui};
+
+pub const COMMAND_NAME: &str = "ping";
+
+pub fn command() -> App<'static, 'static> {
+    SubCommand::with_name(COMMAND_NAME)
+        .about("Send ARP requests to a host and measure the replies")
+        .arg(
+            Arg::with_name("iface")
+                .long("iface")
+                .short("I")
+                .required(true)
+                .takes_value(true)
+                .validator(helpers::is_interface)
+                .help("Interface to send the requests"),
+        )
+        .arg(
+            Arg::with_name("ip")
+                .required(true)
+                .takes_value(true)
+                .validator(helpers::is_ip)
+                .help("IP to ping"),
+        )
+        .arg(
+            Arg::with_name("count")
+                .long("count")
+                .short("c")
+                .takes_value(true)
+                .validator(helpers::is_u64)
+                .help("Number of requests to send. If none, it won't stop until Ctrl-C"),
+        )
+        .arg(
+            Arg::with_name("interval")
+                .long("interval")
+                .short("i")
+                .takes_value(true)
+                .default_value("1000")
+                .value_name("millis")
+                .validator(helpers::is_positive_u64)
+                .help("Time between requests. Replies received later than this are ignored"),
+        )
+        .arg(filter::arg())
+        .arg(oui::arg())
+        .arg(
+            Arg::with_name("verbosity")
+                .short("v")
+                .multiple(true)
+                .help("Increase message verbosity"),
+        )
+}
+
+pub struct Arguments {
+    pub iface: NetworkInterface,
+    pub ip: Ipv4Addr,
+    pub count: Option<u64>,
+    pub interval: Duration,
+    pub filter: Option<String>,
+    pub oui_file: Option<String>,
+    pub verbosity: usize,
+}
+
+impl<'a> Arguments {
+    pub fn parse(matches: &'a ArgMatches) -> Arguments {
+        let iface =
+            helpers::lookup_interface(matches.value_of("iface").unwrap())
+                .unwrap();
+
+        Self {
+            iface,
+            ip: matches.value_of("ip").unwrap().parse().unwrap(),
+            count: matches.value_of("count").map(|c| c.parse().unwrap()),
+            interval: Duration::from_millis(
+                matches.value_of("interval").unwrap().parse().unwrap(),
+            ),
+            filter: filter::parse(matches),
+            oui_file: oui::parse(matches),
+            verbosity: matches.occurrences_of("verbosity") as usize,
+        }
+    }
+}
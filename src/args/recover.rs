@@ -0,0 +1,81 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+use pnet::datalink::NetworkInterface;
+use std::time::Duration;
+
+use super::{helpers, oui};
+
+pub const COMMAND_NAME: &str = "recover";
+
+pub fn command() -> App<'static, 'static> {
+    SubCommand::with_name(COMMAND_NAME)
+        .about("Recover the victims of an interrupted spoof from its session file")
+        .arg(
+            Arg::with_name("session")
+                .required(true)
+                .takes_value(true)
+                .help("Session file saved by spoof, which is removed once the victims are recovered"),
+        )
+        .arg(
+            Arg::with_name("iface")
+                .long("iface")
+                .short("I")
+                .takes_value(true)
+                .validator(helpers::is_interface)
+                .help("Interface to send the packets. If none, the interface of the session will be used"),
+        )
+        .arg(
+            Arg::with_name("delay")
+                .long("delay")
+                .short("d")
+                .takes_value(true)
+                .default_value("1000")
+                .value_name("millis")
+                .validator(helpers::is_u64)
+                .help("Delay between ARP recover packets"),
+        )
+        .arg(
+            Arg::with_name("verify")
+                .long("verify")
+                .takes_value(true)
+                .default_value("10")
+                .value_name("seconds")
+                .validator(helpers::is_u64)
+                .help("Max seconds to verify that the victims are recovered, resending the correct mappings to the ones still poisoned. Longer than the 3 seconds of spoof, where it delays the exit after Ctrl-C, since recover is run on purpose. 0 to not verify"),
+        )
+        .arg(oui::arg())
+        .arg(
+            Arg::with_name("verbosity")
+                .short("v")
+                .multiple(true)
+                .help("Increase message verbosity"),
+        )
+}
+
+pub struct Arguments {
+    pub session: String,
+    pub iface: Option<NetworkInterface>,
+    pub delay: Duration,
+    pub verify: Option<Duration>,
+    pub oui_file: Option<String>,
+    pub verbosity: usize,
+}
+
+impl<'a> Arguments {
+    pub fn parse(matches: &'a ArgMatches) -> Arguments {
+        Self {
+            session: matches.value_of("session").unwrap().to_string(),
+            iface: matches
+                .value_of("iface")
+                .map(|iface| helpers::lookup_interface(iface).unwrap()),
+            delay: Duration::from_millis(
+                matches.value_of("delay").unwrap().parse().unwrap(),
+            ),
+            verify: match matches.value_of("verify").unwrap().parse().unwrap() {
+                0 => None,
+                secs => Some(Duration::from_secs(secs)),
+            },
+            oui_file: oui::parse(matches),
+            verbosity: matches.occurrences_of("verbosity") as usize,
+        }
+    }
+}
@@ -1,11 +1,14 @@
+use crate::pcap::PcapOptions;
+use crate::validators::{self, IpRange};
 use std::net::Ipv4Addr;
+use std::time::Duration;
 use std::{collections::HashSet, str::FromStr};
 
 use clap::{App, Arg, ArgMatches, SubCommand, Values};
 use pnet::ipnetwork::Ipv4Network;
 use pnet::{datalink::NetworkInterface, util::MacAddr};
 
-use super::helpers;
+use super::{filter, helpers, oui, pcap};
 
 pub const COMMAND_NAME: &str = "reply";
 
@@ -28,14 +31,22 @@ pub fn command() -> App<'static, 'static> {
                 .validator(helpers::is_mac)
                 .help("Use the given MAC to answer requests. If none, the interface MAC will be used")
         )
+        .arg(
+            Arg::with_name("mac-map")
+                .long("mac-map")
+                .takes_value(true)
+                .value_name("file")
+                .conflicts_with("mac")
+                .help("File with the MAC to answer for each IP, network or range (lines of '<ips> <mac>', with '*' for the rest of IPs). It is reloaded on SIGHUP")
+        )
         .arg(
             Arg::with_name("match-src-ips")
                 .long("match-src-ips")
                 .short("s")
                 .takes_value(true)
                 .use_delimiter(true)
-                .validator(helpers::is_ip_or_net)
-                .help("Reply to ARP request with the given source ips"),
+                .validator(helpers::is_ip_net_or_range)
+                .help("Reply to ARP request with the given source ips, networks or ranges (first-last)"),
         )
         .arg(
             Arg::with_name("filter-src-ips")
@@ -43,9 +54,8 @@ pub fn command() -> App<'static, 'static> {
                 .short("S")
                 .takes_value(true)
                 .use_delimiter(true)
-                .validator(helpers::is_ip_or_net)
-                .help("Not reply to ARP request with the given source ips")
-                .conflicts_with("match-src-ips"),
+                .validator(helpers::is_ip_net_or_range)
+                .help("Not reply to ARP request with the given source ips, networks or ranges (first-last)"),
         )
         .arg(
             Arg::with_name("match-dst-ips")
@@ -53,8 +63,8 @@ pub fn command() -> App<'static, 'static> {
                 .short("d")
                 .takes_value(true)
                 .use_delimiter(true)
-                .validator(helpers::is_ip_or_net)
-                .help("Reply to ARP requests that ask for the given ips"),
+                .validator(helpers::is_ip_net_or_range)
+                .help("Reply to ARP requests that ask for the given ips, networks or ranges (first-last)"),
         )
         .arg(
             Arg::with_name("filter-dst-ips")
@@ -62,9 +72,8 @@ pub fn command() -> App<'static, 'static> {
                 .short("D")
                 .takes_value(true)
                 .use_delimiter(true)
-                .validator(helpers::is_ip_or_net)
-                .help("Not reply to ARP requests that ask for the given ips")
-                .conflicts_with("match-dst-ips"),
+                .validator(helpers::is_ip_net_or_range)
+                .help("Not reply to ARP requests that ask for the given ips, networks or ranges (first-last)"),
         )
         .arg(
             Arg::with_name("match-src-macs")
@@ -85,6 +94,41 @@ pub fn command() -> App<'static, 'static> {
                 .help("Not reply to ARP requests with the given source MACs")
                 .conflicts_with("match-src-macs"),
         )
+        .arg(
+            Arg::with_name("tarpit")
+                .long("tarpit")
+                .help("Only answer for the IPs that nobody else claims, when their requests are repeated without a reply, and stop answering an IP when its owner shows up. On switched networks the replies of the owners are usually not seen, so only the repeated requests are reliable"),
+        )
+        .arg(
+            Arg::with_name("tarpit-grace")
+                .long("tarpit-grace")
+                .takes_value(true)
+                .value_name("seconds")
+                .validator(helpers::is_u64)
+                .requires("tarpit")
+                .help("Also answer an IP in tarpit mode when no genuine reply is seen within this time. Only use it if the replies to other hosts are seen (hub, mirror port), otherwise live hosts are trapped"),
+        )
+        .arg(
+            Arg::with_name("tarpit-requests")
+                .long("tarpit-requests")
+                .takes_value(true)
+                .value_name("N")
+                .validator(helpers::is_positive_u32)
+                .requires("tarpit")
+                .help("Answer an IP in tarpit mode after N requests without a reply within 30 seconds (3 by default)"),
+        )
+        .arg(
+            Arg::with_name("tarpit-owner-timeout")
+                .long("tarpit-owner-timeout")
+                .takes_value(true)
+                .value_name("seconds")
+                .validator(helpers::is_u64)
+                .requires("tarpit")
+                .help("Time without seeing the owner of an IP in tarpit mode before it can be answered again (300 by default)"),
+        )
+        .arg(filter::arg())
+        .args(&pcap::args())
+        .arg(oui::arg())
         .arg(
             Arg::with_name("verbosity")
                 .short("v")
@@ -93,10 +137,36 @@ pub fn command() -> App<'static, 'static> {
         )
 }
 
+/// IPs given as single addresses, networks and ranges, which are kept apart
+/// to check them without expanding the networks and ranges.
+#[derive(Debug, Default)]
+pub struct Ips {
+    pub ips: HashSet<Ipv4Addr>,
+    pub nets: Vec<Ipv4Network>,
+    pub ranges: Vec<IpRange>,
+}
+
+impl Ips {
+    pub fn is_empty(&self) -> bool {
+        return self.ips.is_empty()
+            && self.nets.is_empty()
+            && self.ranges.is_empty();
+    }
+
+    pub fn contains(&self, ip: Ipv4Addr) -> bool {
+        return self.ips.contains(&ip)
+            || self.nets.iter().any(|net| net.contains(ip))
+            || self
+                .ranges
+                .iter()
+                .any(|(first, last)| *first <= ip && ip <= *last);
+    }
+}
+
 #[derive(Debug)]
 pub enum IpsVal {
-    Match(HashSet<Ipv4Addr>),
-    Filter(HashSet<Ipv4Addr>),
+    Match(Ips),
+    Filter(Ips),
 }
 
 #[derive(Debug)]
@@ -109,9 +179,17 @@ pub enum MacsVal {
 pub struct Arguments {
     pub iface: NetworkInterface,
     pub mac: Option<MacAddr>,
-    pub src_ips: Option<IpsVal>,
+    pub mac_map: Option<String>,
+    pub src_ips: Vec<IpsVal>,
     pub src_macs: Option<MacsVal>,
-    pub dst_ips: Option<IpsVal>,
+    pub dst_ips: Vec<IpsVal>,
+    pub filter: Option<String>,
+    pub tarpit: bool,
+    pub tarpit_grace: Option<Duration>,
+    pub tarpit_requests: u32,
+    pub tarpit_owner_timeout: Duration,
+    pub pcap: Option<PcapOptions>,
+    pub oui_file: Option<String>,
     pub verbosity: usize,
 }
 
@@ -121,12 +199,15 @@ impl<'a> Arguments {
             helpers::lookup_interface(matches.value_of("iface").unwrap())
                 .unwrap();
 
-        let src_ips = match matches.values_of("match-src-ips") {
-            Some(msi) => Some(IpsVal::Match(parse_ips(msi))),
-            None => matches
-                .values_of("filter-src-ips")
-                .map(|fsi| IpsVal::Filter(parse_ips(fsi))),
-        };
+        // match and filter can be combined, to reply for a network except
+        // some of its IPs
+        let mut src_ips = Vec::new();
+        if let Some(msi) = matches.values_of("match-src-ips") {
+            src_ips.push(IpsVal::Match(parse_ips(msi)));
+        }
+        if let Some(fsi) = matches.values_of("filter-src-ips") {
+            src_ips.push(IpsVal::Filter(parse_ips(fsi)));
+        }
 
         let src_macs = match matches.values_of("match-src-macs") {
             Some(msm) => Some(MacsVal::Match(parse_macs(msm))),
@@ -135,25 +216,44 @@ impl<'a> Arguments {
                 .map(|fsm| MacsVal::Filter(parse_macs(fsm))),
         };
 
-        let dst_ips = match matches.values_of("match-dst-ips") {
-            Some(msi) => Some(IpsVal::Match(parse_ips(msi))),
-            None => matches
-                .values_of("filter-dst-ips")
-                .map(|fsi| IpsVal::Filter(parse_ips(fsi))),
-        };
+        let mut dst_ips = Vec::new();
+        if let Some(mdi) = matches.values_of("match-dst-ips") {
+            dst_ips.push(IpsVal::Match(parse_ips(mdi)));
+        }
+        if let Some(fdi) = matches.values_of("filter-dst-ips") {
+            dst_ips.push(IpsVal::Filter(parse_ips(fdi)));
+        }
 
         Self {
             iface,
             mac: matches.value_of("mac").map(|s| s.parse().unwrap()),
+            mac_map: matches.value_of("mac-map").map(|f| f.to_string()),
             src_ips,
             src_macs,
             dst_ips,
+            filter: filter::parse(matches),
+            tarpit: matches.is_present("tarpit"),
+            tarpit_grace: matches
+                .value_of("tarpit-grace")
+                .map(|s| Duration::from_secs(s.parse().unwrap())),
+            tarpit_requests: matches
+                .value_of("tarpit-requests")
+                .map(|n| n.parse().unwrap())
+                .unwrap_or(3),
+            tarpit_owner_timeout: Duration::from_secs(
+                matches
+                    .value_of("tarpit-owner-timeout")
+                    .map(|s| s.parse().unwrap())
+                    .unwrap_or(300),
+            ),
+            pcap: pcap::parse(matches),
+            oui_file: oui::parse(matches),
             verbosity: matches.occurrences_of("verbosity") as usize,
         }
     }
 }
 
-fn parse_macs<'a>(macs_str: Values) -> HashSet<MacAddr> {
+fn parse_macs(macs_str: Values) -> HashSet<MacAddr> {
     let mut macs = HashSet::new();
 
     for mac_str in macs_str {
@@ -163,23 +263,46 @@ fn parse_macs<'a>(macs_str: Values) -> HashSet<MacAddr> {
     return macs;
 }
 
-fn parse_ips<'a>(ips_nets: Values) -> HashSet<Ipv4Addr> {
-    let mut ips = HashSet::new();
-
-    ips_nets
-        .into_iter()
-        .for_each(|ip_net| match ip_net.parse::<Ipv4Addr>() {
-            Ok(ip) => {
-                ips.insert(ip);
-            }
-            Err(_) => {
-                let net = ip_net.parse::<Ipv4Network>().unwrap();
+pub fn parse_ips(ips_nets: Values) -> Ips {
+    let mut ips = Ips::default();
 
-                for ip in net.iter() {
-                    ips.insert(ip);
-                }
-            }
-        });
+    for ip_net in ips_nets {
+        if let Ok(ip) = ip_net.parse::<Ipv4Addr>() {
+            ips.ips.insert(ip);
+        } else if let Ok(net) = ip_net.parse::<Ipv4Network>() {
+            ips.nets.push(net);
+        } else {
+            ips.ranges.push(validators::parse_ip_range(ip_net).unwrap());
+        }
+    }
 
     return ips;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ips_contain_addresses_networks_and_ranges() {
+        let matches = App::new("test")
+            .arg(Arg::with_name("ips").multiple(true))
+            .get_matches_from(vec![
+                "test",
+                "10.0.0.1",
+                "192.168.1.0/24",
+                "172.16.0.10-172.16.0.20",
+            ]);
+        let ips = parse_ips(matches.values_of("ips").unwrap());
+
+        assert!(!ips.is_empty());
+        assert!(ips.contains("10.0.0.1".parse().unwrap()));
+        assert!(!ips.contains("10.0.0.2".parse().unwrap()));
+        assert!(ips.contains("192.168.1.255".parse().unwrap()));
+        assert!(!ips.contains("192.168.2.0".parse().unwrap()));
+        assert!(ips.contains("172.16.0.10".parse().unwrap()));
+        assert!(ips.contains("172.16.0.20".parse().unwrap()));
+        assert!(!ips.contains("172.16.0.21".parse().unwrap()));
+        assert!(Ips::default().is_empty());
+    }
+}
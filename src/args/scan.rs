@@ -3,7 +3,7 @@ use pnet::ipnetwork::{Ipv4Network};
 use pnet::datalink::NetworkInterface;
 use std::{net::Ipv4Addr, time::Duration};
 
-use super::helpers;
+use super::{filter, helpers, oui};
 
 pub const COMMAND_NAME: &str = "scan";
 
@@ -33,17 +33,64 @@ pub fn command() -> App<'static, 'static> {
                 .default_value("5000")
                 .value_name("millis")
                 .validator(helpers::is_u64)
-                .help("Timeout for ARP requests (to discover victim and spoofed device)"),
+                .help("Time to wait for the reply of each ARP request"),
+        )
+        .arg(
+            Arg::with_name("rate")
+                .long("rate")
+                .short("r")
+                .takes_value(true)
+                .default_value("1000")
+                .value_name("pps")
+                .validator(helpers::is_u64)
+                .help("Max number of ARP requests sent per second. If 0, there is no limit"),
         )
         .arg(
             Arg::with_name("workers")
                 .long("workers")
                 .short("w")
                 .takes_value(true)
-                .default_value("1")
+                .hidden(true)
                 .validator(helpers::is_u64)
-                .help("Concurrent workers to send requests"),
+                .help("Deprecated, ignored. Use --rate to control the sending speed"),
+        )
+        .arg(
+            Arg::with_name("retries")
+                .long("retries")
+                .short("R")
+                .takes_value(true)
+                .default_value("2")
+                .validator(helpers::is_u32)
+                .help("Number of times to request again a host that doesn't reply"),
         )
+        .arg(
+            Arg::with_name("backoff")
+                .long("backoff")
+                .short("b")
+                .takes_value(true)
+                .default_value("200")
+                .value_name("millis")
+                .validator(helpers::is_u64)
+                .help("Time to wait before the first retry of a host, doubled on each retry"),
+        )
+        .arg(
+            Arg::with_name("output-format")
+                .long("output-format")
+                .short("o")
+                .takes_value(true)
+                .value_name("format")
+                .possible_values(&["plain", "json", "csv", "grep"])
+                .default_value("plain")
+                .help("Format of the results: plain (as discovered), json (JSON Lines), csv or grep (sorted list)"),
+        )
+        .arg(
+            Arg::with_name("vendor")
+                .long("vendor")
+                .takes_value(true)
+                .help("Only show hosts whose vendor contains the given text (case insensitive)"),
+        )
+        .arg(filter::arg())
+        .arg(oui::arg())
         .arg(
             Arg::with_name("verbosity")
                 .short("v")
@@ -57,11 +104,26 @@ pub enum Target {
     Net(Ipv4Network),
 }
 
+pub enum OutputFormat {
+    Plain,
+    Json,
+    Csv,
+    Grep,
+}
+
 pub struct Arguments {
     pub iface: NetworkInterface,
     pub target: Option<Target>,
     pub timeout: Duration,
-    pub workers: u64,
+    pub rate: Option<u64>,
+    /// Deprecated, the requests are sent by a single thread.
+    pub workers: Option<u64>,
+    pub retries: u32,
+    pub backoff: Duration,
+    pub output_format: OutputFormat,
+    pub vendor: Option<String>,
+    pub filter: Option<String>,
+    pub oui_file: Option<String>,
     pub verbosity: usize,
 }
 
@@ -73,22 +135,45 @@ impl<'a> Arguments {
 
         Self {
             iface,
-            target: matches.value_of("ip").map(|t| parse_target(t)),
+            target: matches.value_of("ip").map(parse_target),
             timeout: Duration::from_millis(
                 matches.value_of("timeout").unwrap().parse().unwrap(),
             ),
-            workers: matches.value_of("workers").unwrap().parse().unwrap(),
+            rate: match matches.value_of("rate").unwrap().parse().unwrap() {
+                0 => None,
+                rate => Some(rate),
+            },
+            workers: matches.value_of("workers").map(|w| w.parse().unwrap()),
+            retries: matches.value_of("retries").unwrap().parse().unwrap(),
+            backoff: Duration::from_millis(
+                matches.value_of("backoff").unwrap().parse().unwrap(),
+            ),
+            output_format: parse_output_format(
+                matches.value_of("output-format").unwrap(),
+            ),
+            vendor: matches.value_of("vendor").map(|v| v.to_string()),
+            filter: filter::parse(matches),
+            oui_file: oui::parse(matches),
             verbosity: matches.occurrences_of("verbosity") as usize,
         }
     }
 }
 
-fn parse_target(target: &str) -> Target {
-    let ip = target.parse::<Ipv4Addr>();
-    if ip.is_ok() {
-        return Target::Ip(ip.unwrap());
+pub fn parse_target(target: &str) -> Target {
+    if let Ok(ip) = target.parse::<Ipv4Addr>() {
+        return Target::Ip(ip);
     }
 
     let net = target.parse::<Ipv4Network>().unwrap();
     return Target::Net(net);
 }
+
+fn parse_output_format(format: &str) -> OutputFormat {
+    match format {
+        "plain" => OutputFormat::Plain,
+        "json" => OutputFormat::Json,
+        "csv" => OutputFormat::Csv,
+        "grep" => OutputFormat::Grep,
+        _ => unreachable!("Unknown output format"),
+    }
+}
@@ -0,0 +1,64 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+use pnet::datalink::NetworkInterface;
+use std::time::Duration;
+
+use super::{filter, helpers, oui};
+
+pub const COMMAND_NAME: &str = "sniff";
+
+pub fn command() -> App<'static, 'static> {
+    SubCommand::with_name(COMMAND_NAME)
+        .about("Discover hosts passively by listening ARP packets, without sending anything")
+        .arg(
+            Arg::with_name("iface")
+                .long("iface")
+                .short("I")
+                .required(true)
+                .takes_value(true)
+                .validator(helpers::is_interface)
+                .help("Interface to listen"),
+        )
+        .arg(
+            Arg::with_name("duration")
+                .long("duration")
+                .short("t")
+                .takes_value(true)
+                .value_name("seconds")
+                .validator(helpers::is_u64)
+                .help("Time to listen. If none, it won't stop until Ctrl-C"),
+        )
+        .arg(filter::arg())
+        .arg(oui::arg())
+        .arg(
+            Arg::with_name("verbosity")
+                .short("v")
+                .multiple(true)
+                .help("Increase message verbosity"),
+        )
+}
+
+pub struct Arguments {
+    pub iface: NetworkInterface,
+    pub duration: Option<Duration>,
+    pub filter: Option<String>,
+    pub oui_file: Option<String>,
+    pub verbosity: usize,
+}
+
+impl<'a> Arguments {
+    pub fn parse(matches: &'a ArgMatches) -> Arguments {
+        let iface =
+            helpers::lookup_interface(matches.value_of("iface").unwrap())
+                .unwrap();
+
+        Self {
+            iface,
+            duration: matches
+                .value_of("duration")
+                .map(|d| Duration::from_secs(d.parse().unwrap())),
+            filter: filter::parse(matches),
+            oui_file: oui::parse(matches),
+            verbosity: matches.occurrences_of("verbosity") as usize,
+        }
+    }
+}
@@ -1,10 +1,12 @@
+use crate::pcap::PcapOptions;
 use pnet::util::MacAddr;
 use std::{collections::HashSet, net::Ipv4Addr, time::Duration};
 
 use clap::{App, Arg, ArgMatches, SubCommand, Values};
 use pnet::{datalink::NetworkInterface, ipnetwork::Ipv4Network};
 
-use super::helpers;
+use super::reply::{self, Ips};
+use super::{filter, helpers, oui, pcap};
 
 pub const COMMAND_NAME: &str = "spoof";
 
@@ -30,10 +32,16 @@ pub fn command() -> App<'static, 'static> {
         )
         .arg(
             Arg::with_name("gw-ip")
-                .required(true)
                 .takes_value(true)
+                .use_delimiter(true)
                 .validator(helpers::is_ip)
-                .help("IP to impersonate"),
+                .help("IPs to impersonate. If none, the default gateway of the interface will be used"),
+        )
+        .arg(
+            Arg::with_name("mesh")
+                .long("mesh")
+                .conflicts_with("gw-ip")
+                .help("Impersonate every victim to every other victim"),
         )
         .arg(
             Arg::with_name("fake-mac")
@@ -54,6 +62,34 @@ pub fn command() -> App<'static, 'static> {
                 .validator(helpers::is_ip)
                 .help("IP to get the MAC to poison the ARP tables. If none, the MAC of the selected interface will be used")
         )
+        .arg(
+            Arg::with_name("arp-cache")
+                .long("arp-cache")
+                .short("a")
+                .help("Take the MACs of the gateway and victims from the kernel ARP cache, if present, instead of requesting them"),
+        )
+        .arg(
+            Arg::with_name("retry-unresolved")
+                .long("retry-unresolved")
+                .takes_value(true)
+                .value_name("seconds")
+                .validator(helpers::is_u64)
+                .help("Try again to get the MAC of the victims that didn't reply every given seconds, and spoof them once resolved"),
+        )
+        .arg(
+            Arg::with_name("discover")
+                .long("discover")
+                .help("Keep listening the network to spoof the hosts of the victims range that appear after the start"),
+        )
+        .arg(
+            Arg::with_name("expire")
+                .long("expire")
+                .takes_value(true)
+                .value_name("seconds")
+                .validator(helpers::is_u64)
+                .requires("discover")
+                .help("Stop spoofing the victims that are silent for the given seconds"),
+        )
         .arg(
             Arg::with_name("timeout")
                 .long("timeout")
@@ -74,6 +110,15 @@ pub fn command() -> App<'static, 'static> {
                 .validator(helpers::is_u64)
                 .help("Delay between ARP spoof packets"),
         )
+        .arg(
+            Arg::with_name("method")
+                .long("method")
+                .takes_value(true)
+                .value_name("method")
+                .possible_values(&["reply", "request", "gratuitous-request", "gratuitous-reply", "mixed"])
+                .default_value("reply")
+                .help("Packets used to poison (and recover) the ARP tables: reply (unicast reply), request (unicast request), gratuitous-request and gratuitous-reply (broadcast announcements), or mixed (unicast reply and request)"),
+        )
         .arg(
             Arg::with_name("count")
                 .long("count")
@@ -100,6 +145,42 @@ pub fn command() -> App<'static, 'static> {
                 .short("F")
                 .help("Enable IP forwarding"),
         )
+        .arg(
+            Arg::with_name("session")
+                .long("session")
+                .takes_value(true)
+                .value_name("file")
+                .help("File to save the session, used by the recover command to recover the victims if the spoofing is interrupted. By default arplayer-<iface>.session"),
+        )
+        .arg(
+            Arg::with_name("verify")
+                .long("verify")
+                .takes_value(true)
+                .default_value("3")
+                .value_name("seconds")
+                .validator(helpers::is_u64)
+                .help("Max seconds to verify that the victims are recovered, resending the correct mappings to the ones still poisoned. It delays the exit when some victim sends no traffic. 0 to not verify"),
+        )
+        .arg(
+            Arg::with_name("relay")
+                .long("relay")
+                .short("R")
+                .conflicts_with_all(&["forward", "fake-mac", "fake-ip"])
+                .help("Relay the intercepted packets in userspace, without enabling the kernel IP forwarding"),
+        )
+        .arg(
+            Arg::with_name("relay-drop")
+                .long("relay-drop")
+                .takes_value(true)
+                .use_delimiter(true)
+                .value_name("ip")
+                .validator(helpers::is_ip_net_or_range)
+                .requires("relay")
+                .help("Drop the intercepted packets from or to the given IPs, networks or ranges (first-last) instead of relaying them"),
+        )
+        .arg(filter::arg())
+        .args(&pcap::args())
+        .arg(oui::arg())
         .arg(
             Arg::with_name("verbosity")
                 .short("v")
@@ -108,20 +189,42 @@ pub fn command() -> App<'static, 'static> {
         )
 }
 
+#[derive(Debug, Clone, Copy)]
+pub enum Method {
+    Reply,
+    Request,
+    GratuitousRequest,
+    GratuitousReply,
+    Mixed,
+}
+
 #[derive(Debug)]
 pub struct Arguments {
     pub iface: NetworkInterface,
     pub victim_ips: HashSet<Ipv4Addr>,
-    pub gw_ip: Ipv4Addr,
+    pub gw_ips: Vec<Ipv4Addr>,
+    pub mesh: bool,
     pub fake_ip: Option<Ipv4Addr>,
     pub fake_mac: Option<MacAddr>,
     pub delay: Duration,
     pub timeout: Duration,
+    pub oui_file: Option<String>,
     pub verbosity: usize,
     pub recover: bool,
     pub count: Option<u64>,
     pub bidirectional: bool,
     pub forward: bool,
+    pub arp_cache: bool,
+    pub retry_unresolved: Option<Duration>,
+    pub discover: bool,
+    pub expire: Option<Duration>,
+    pub method: Method,
+    pub session: Option<String>,
+    pub verify: Option<Duration>,
+    pub relay: bool,
+    pub relay_drop: Ips,
+    pub filter: Option<String>,
+    pub pcap: Option<PcapOptions>,
 }
 
 impl<'a> Arguments {
@@ -135,12 +238,14 @@ impl<'a> Arguments {
             victim_ips: parse_victim_ips(
                 matches.values_of("victim-ip").unwrap(),
             ),
-            gw_ip: matches
-                .value_of("gw-ip")
-                .map(|ip| ip.parse().unwrap())
-                .unwrap(),
+            gw_ips: matches
+                .values_of("gw-ip")
+                .map(|ips| ips.map(|ip| ip.parse().unwrap()).collect())
+                .unwrap_or_default(),
+            mesh: matches.is_present("mesh"),
             fake_ip: matches.value_of("fake-ip").map(|ip| ip.parse().unwrap()),
             fake_mac: matches.value_of("fake-mac").map(|mac| mac.parse().unwrap()),
+            oui_file: oui::parse(matches),
             verbosity: matches.occurrences_of("verbosity") as usize,
             timeout: Duration::from_millis(
                 matches.value_of("timeout").unwrap().parse().unwrap(),
@@ -151,12 +256,44 @@ impl<'a> Arguments {
             recover: !matches.is_present("no-recover"),
             bidirectional: matches.is_present("bidirectional"),
             forward: matches.is_present("forward"),
+            arp_cache: matches.is_present("arp-cache"),
+            retry_unresolved: matches
+                .value_of("retry-unresolved")
+                .map(|s| Duration::from_secs(s.parse().unwrap())),
+            discover: matches.is_present("discover"),
+            expire: matches
+                .value_of("expire")
+                .map(|s| Duration::from_secs(s.parse().unwrap())),
             count: matches.value_of("count").map(|c| c.parse().unwrap()),
+            method: parse_method(matches.value_of("method").unwrap()),
+            session: matches.value_of("session").map(|f| f.to_string()),
+            relay: matches.is_present("relay"),
+            filter: filter::parse(matches),
+            pcap: pcap::parse(matches),
+            relay_drop: matches
+                .values_of("relay-drop")
+                .map(reply::parse_ips)
+                .unwrap_or_default(),
+            verify: match matches.value_of("verify").unwrap().parse().unwrap() {
+                0 => None,
+                secs => Some(Duration::from_secs(secs)),
+            },
         }
     }
 }
 
-fn parse_victim_ips<'a>(ips_nets: Values) -> HashSet<Ipv4Addr> {
+fn parse_method(method: &str) -> Method {
+    match method {
+        "reply" => Method::Reply,
+        "request" => Method::Request,
+        "gratuitous-request" => Method::GratuitousRequest,
+        "gratuitous-reply" => Method::GratuitousReply,
+        "mixed" => Method::Mixed,
+        _ => unreachable!("Unknown spoof method"),
+    }
+}
+
+fn parse_victim_ips(ips_nets: Values) -> HashSet<Ipv4Addr> {
     let mut ips = HashSet::new();
 
     ips_nets
@@ -23,8 +23,10 @@ pub fn request_arp(
 ) -> Result<MacAddr, String> {
     let ethernet_packet = ether_arp_request(target_ip, source_ip, source_mac);
 
-    let mut config = Config::default();
-    config.read_timeout = Some(timeout);
+    let config = Config {
+        read_timeout: Some(timeout),
+        ..Default::default()
+    };
 
     let (mut sender, mut receiver) = new_ether_channel(iface, config)?;
 
@@ -33,13 +35,11 @@ pub fn request_arp(
         .ok_or("Error sending packet")?
         .map_err(|e| format!("Error sending packet: {}", e))?;
 
-    let timeout = timeout;
-
     let start_time = Instant::now();
     loop {
         let now = Instant::now();
         if now.duration_since(start_time) > timeout {
-            return Err(format!("Error receiving packet: Timed out"));
+            return Err("Error receiving packet: Timed out".to_string());
         }
         let buf = receiver
             .next()
@@ -59,14 +59,18 @@ pub fn request_arp(
     }
 }
 
+pub type EtherChannel = (Box<dyn DataLinkSender>, Box<dyn DataLinkReceiver>);
+
 pub fn new_ether_channel(
     iface: &NetworkInterface,
     config: Config,
-) -> Result<(Box<dyn DataLinkSender>, Box<dyn DataLinkReceiver>), String> {
+) -> Result<EtherChannel, String> {
     let (sender, receiver) = match datalink::channel(iface, config) {
         Ok(Channel::Ethernet(tx, rx)) => (tx, rx),
         Ok(_) => {
-            return Err(format!("Error creating channel: Unknown channel type"))
+            return Err(
+                "Error creating channel: Unknown channel type".to_string()
+            )
         }
         Err(e) => return Err(format!("Error creating channel: {}", e)),
     };
@@ -75,7 +79,7 @@ pub fn new_ether_channel(
 }
 
 pub fn is_arp(buf: &[u8]) -> bool {
-    let ethernet = match EthernetPacket::new(&buf[..]) {
+    let ethernet = match EthernetPacket::new(buf) {
         Some(ether) => ether,
         None => {
             return false;
@@ -85,6 +89,48 @@ pub fn is_arp(buf: &[u8]) -> bool {
     return ethernet.get_ethertype() == EtherTypes::Arp;
 }
 
+/// Returns the sender IP and MAC of the frame if it is an ARP reply to the
+/// given addresses, so the replies to the requests of other hosts are ignored.
+pub fn parse_arp_reply(
+    buf: &[u8],
+    my_ip: Ipv4Addr,
+    my_mac: MacAddr,
+) -> Option<(Ipv4Addr, MacAddr)> {
+    if !is_arp(buf) {
+        return None;
+    }
+
+    let arp_msg =
+        ArpPacket::new(&buf[MutableEthernetPacket::minimum_packet_size()..])?;
+
+    if arp_msg.get_operation() != ArpOperations::Reply
+        || arp_msg.get_target_proto_addr() != my_ip
+        || arp_msg.get_target_hw_addr() != my_mac
+    {
+        return None;
+    }
+
+    return Some((
+        arp_msg.get_sender_proto_addr(),
+        arp_msg.get_sender_hw_addr(),
+    ));
+}
+
+/// Sender of any ARP packet, request or reply.
+pub fn parse_arp_sender(buf: &[u8]) -> Option<(Ipv4Addr, MacAddr)> {
+    if !is_arp(buf) {
+        return None;
+    }
+
+    let arp_msg =
+        ArpPacket::new(&buf[MutableEthernetPacket::minimum_packet_size()..])?;
+
+    return Some((
+        arp_msg.get_sender_proto_addr(),
+        arp_msg.get_sender_hw_addr(),
+    ));
+}
+
 fn is_arp_reply_for(arp: &ArpPacket, target_ip: Ipv4Addr) -> bool {
     return arp.get_operation() == ArpOperations::Reply
         && arp.get_sender_proto_addr() == target_ip;
@@ -125,6 +171,50 @@ pub fn ether_arp_request<'a>(
     return ethernet_packet;
 }
 
+/// ARP request sent only to the target, so the sender addresses are added to
+/// its ARP cache without being announced to the rest of the network.
+pub fn ether_arp_unicast_request<'a>(
+    target_ip: Ipv4Addr,
+    target_mac: MacAddr,
+    source_ip: Ipv4Addr,
+    source_mac: MacAddr,
+) -> EthernetPacket<'a> {
+    let arp_packet = arp_request(target_ip, source_ip, source_mac);
+    return new_ether_arp(target_mac, source_mac, &arp_packet);
+}
+
+/// Gratuitous ARP request broadcast, that announces the source IP by asking
+/// for itself.
+pub fn ether_gratuitous_arp_request<'a>(
+    source_ip: Ipv4Addr,
+    source_mac: MacAddr,
+) -> EthernetPacket<'a> {
+    let arp_packet = arp_request(source_ip, source_ip, source_mac);
+    return ether_arp_broadcast(source_mac, &arp_packet);
+}
+
+/// Gratuitous ARP reply broadcast, that announces the source IP without
+/// being requested.
+pub fn ether_gratuitous_arp_reply<'a>(
+    source_ip: Ipv4Addr,
+    source_mac: MacAddr,
+) -> EthernetPacket<'a> {
+    let arp_packet =
+        new_arp_reply(source_ip, MacAddr::broadcast(), source_ip, source_mac);
+    return ether_arp_broadcast(source_mac, &arp_packet);
+}
+
+/// ARP probe (RFC 5227) broadcast that asks for the target IP with an
+/// unspecified sender IP, so the ARP caches of other hosts are not altered.
+pub fn ether_arp_probe<'a>(
+    target_ip: Ipv4Addr,
+    source_mac: MacAddr,
+) -> EthernetPacket<'a> {
+    let arp_packet = arp_probe(target_ip, source_mac);
+    let ethernet_packet = ether_arp_broadcast(source_mac, &arp_packet);
+    return ethernet_packet;
+}
+
 pub fn ether_arp_broadcast<'a>(
     source_mac: MacAddr,
     arp_packet: &ArpPacket<'a>,
@@ -170,6 +260,13 @@ pub fn arp_request<'a>(
     return arp_packet.consume_to_immutable();
 }
 
+pub fn arp_probe<'a>(
+    target_ip: Ipv4Addr,
+    source_mac: MacAddr,
+) -> ArpPacket<'a> {
+    return arp_request(target_ip, Ipv4Addr::UNSPECIFIED, source_mac);
+}
+
 pub fn new_arp_reply<'a>(
     target_ip: Ipv4Addr,
     target_mac: MacAddr,
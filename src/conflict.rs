@@ -0,0 +1,177 @@
+use crate::args::{self, scan::Target};
+use crate::arp;
+use crate::oui::OuiDb;
+use crate::scanner::{self, RateLimiter};
+use crate::validators::{self, Validator};
+use log::{debug, info};
+use pnet::{
+    datalink::{Config, DataLinkSender, NetworkInterface},
+    ipnetwork::Ipv4Network,
+    packet::Packet,
+    util::MacAddr,
+};
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::net::Ipv4Addr;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Max time the receiver blocks before checking if the probes were sent.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+pub fn main_conflict(args: args::conflict::Arguments) -> Result<(), String> {
+    let source_mac = args.iface.mac.ok_or_else(|| {
+        format!("Unable to get the MAC address of {} interface", args.iface)
+    })?;
+    let oui_db = OuiDb::new(args.oui_file.as_deref())?;
+
+    let net = match args.target {
+        Some(Target::Ip(ip)) => Ipv4Network::new(ip, 32).unwrap(),
+        Some(Target::Net(net)) => net,
+        None => *arp::get_iface_ipv4_network(&args.iface).ok_or_else(|| {
+            format!(
+                "Unable to get the Ipv4 address of {} interface",
+                args.iface.name
+            )
+        })?,
+    };
+    info!("Probing {}", net);
+
+    let filter = match &args.filter {
+        Some(filter) => Some(validators::parse_filter(filter)?),
+        None => None,
+    };
+
+    let claims = probe_conflicts(
+        &args.iface,
+        net,
+        source_mac,
+        args.probes,
+        args.rate,
+        args.wait,
+        filter,
+    )?;
+
+    let mut conflicts = 0;
+    for (ip, macs) in claims.iter() {
+        let macs_desc = macs
+            .iter()
+            .map(|mac| format!("{} ({})", mac, oui_db.describe(*mac)))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        if macs.len() > 1 {
+            conflicts += 1;
+            println!("CONFLICT {} claimed by {}", ip, macs_desc);
+        } else {
+            info!("{} claimed by {}", ip, macs_desc);
+        }
+    }
+
+    eprintln!("{} IPs in use, {} in conflict", claims.len(), conflicts);
+
+    return Ok(());
+}
+
+/// Sends ARP probes for every IP of the network and returns the distinct MACs
+/// that replied for each IP. The replies that don't match the filter, if any,
+/// are ignored.
+fn probe_conflicts(
+    iface: &NetworkInterface,
+    net: Ipv4Network,
+    source_mac: MacAddr,
+    probes: u32,
+    rate: Option<u64>,
+    wait: Duration,
+    filter: Option<Validator>,
+) -> Result<BTreeMap<Ipv4Addr, Vec<MacAddr>>, String> {
+    let config = Config {
+        read_timeout: Some(POLL_INTERVAL),
+        ..Default::default()
+    };
+    let (sender, mut receiver) = arp::new_ether_channel(iface, config)?;
+
+    let sending = Arc::new(AtomicBool::new(true));
+    let send_thread = {
+        let sending = sending.clone();
+        thread::spawn(move || {
+            let res = send_probes(sender, net, source_mac, probes, rate);
+            sending.store(false, Ordering::SeqCst);
+            res
+        })
+    };
+
+    let mut claims: BTreeMap<Ipv4Addr, Vec<MacAddr>> = BTreeMap::new();
+    let mut sent_at = None;
+
+    loop {
+        match receiver.next() {
+            Ok(buf) => {
+                if let Some((ip, mac)) =
+                    arp::parse_arp_reply(buf, Ipv4Addr::UNSPECIFIED, source_mac)
+                {
+                    let matches = match &filter {
+                        Some(filter) => filter.is_valid(buf),
+                        None => true,
+                    };
+                    if matches && net.contains(ip) {
+                        let macs = claims.entry(ip).or_default();
+                        if !macs.contains(&mac) {
+                            debug!("{} replied from {}", ip, mac);
+                            macs.push(mac);
+                        }
+                    }
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::TimedOut => {}
+            Err(e) => return Err(format!("Error receiving packet: {}", e)),
+        }
+
+        if sending.load(Ordering::SeqCst) {
+            continue;
+        }
+
+        match sent_at {
+            None => sent_at = Some(Instant::now()),
+            Some(sent_at) => {
+                if sent_at.elapsed() >= wait {
+                    break;
+                }
+            }
+        }
+    }
+
+    send_thread
+        .join()
+        .map_err(|_| "Error sending packets: Sender thread panicked")??;
+
+    return Ok(claims);
+}
+
+fn send_probes(
+    mut sender: Box<dyn DataLinkSender>,
+    net: Ipv4Network,
+    source_mac: MacAddr,
+    probes: u32,
+    rate: Option<u64>,
+) -> Result<(), String> {
+    let mut limiter = RateLimiter::new(rate);
+
+    for _ in 0..probes {
+        for target_ip in scanner::host_ips(net) {
+            limiter.wait();
+
+            let packet = arp::ether_arp_probe(target_ip, source_mac);
+            sender
+                .send_to(packet.packet(), None)
+                .ok_or("Error sending packet")?
+                .map_err(|e| format!("Error sending packet: {}", e))?;
+        }
+    }
+
+    return Ok(());
+}
@@ -0,0 +1,311 @@
+/// Tokenizer and recursive-descent parser of the boolean expressions used by
+/// the filters. The primitives are parsed by a `Builder`, which also combines
+/// them, while the operators are shared:
+///
+/// ```text
+/// expr    = and ("or" | "||") and ...
+/// and     = not ("and" | "&&") not ...
+/// not     = ("not" | "!") not | primary
+/// primary = "(" expr ")" | primitive
+/// ```
+///
+/// So `not` binds tighter than `and`, and `and` tighter than `or`, unless the
+/// builder gives them the same precedence, as in BPF, and then they are
+/// grouped from left to right, so `a or b and c` is `(a or b) and c`. The
+/// parentheses and the symbolic operators don't need spaces around them, as
+/// in `!(src ip 10.0.0.1||gratuitous)`.
+pub trait Builder {
+    type Filter;
+
+    /// Parses the primitive that starts with the given token, taking its
+    /// arguments from the tokens.
+    fn primitive(
+        &self,
+        token: &str,
+        tokens: &mut Tokens,
+    ) -> Result<Self::Filter, String>;
+
+    fn and(&self, f1: Self::Filter, f2: Self::Filter) -> Self::Filter;
+
+    fn or(&self, f1: Self::Filter, f2: Self::Filter) -> Self::Filter;
+
+    fn not(&self, f: Self::Filter) -> Self::Filter;
+
+    /// Whether `and` and `or` have the same precedence.
+    fn same_precedence(&self) -> bool {
+        return false;
+    }
+}
+
+/// Max number of nested parentheses and negations, which bounds the recursion
+/// of the parser.
+const MAX_DEPTH: usize = 64;
+
+/// Parses the expression with the primitives of the builder.
+pub fn parse<B: Builder>(expr: &str, builder: &B) -> Result<B::Filter, String> {
+    let mut parser = Parser {
+        tokens: Tokens::new(expr),
+        builder,
+        depth: 0,
+    };
+    let filter = parser
+        .parse_or()
+        .map_err(|e| format!("Invalid filter '{}': {}", expr, e))?;
+
+    if let Some(token) = parser.tokens.peek() {
+        return Err(format!(
+            "Invalid filter '{}': Unexpected '{}'",
+            expr, token
+        ));
+    }
+
+    return Ok(filter);
+}
+
+pub struct Tokens {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl Tokens {
+    fn new(expr: &str) -> Self {
+        let mut spaced = expr.to_string();
+        for symbol in &["(", ")", "!", "&&", "||"] {
+            spaced = spaced.replace(symbol, &format!(" {} ", symbol));
+        }
+        let tokens = spaced.split_whitespace().map(|t| t.to_string()).collect();
+        return Self { tokens, pos: 0 };
+    }
+
+    pub fn peek(&self) -> Option<&str> {
+        return self.tokens.get(self.pos).map(|t| t.as_str());
+    }
+
+    pub fn next(&mut self) -> Result<String, String> {
+        let token =
+            self.tokens.get(self.pos).cloned().ok_or("Unexpected end")?;
+        self.pos += 1;
+        return Ok(token);
+    }
+
+    /// Consumes the next token if it is one of the given ones.
+    pub fn next_if(&mut self, expected: &[&str]) -> Option<String> {
+        let token = self.peek()?;
+        if !expected.contains(&token) {
+            return None;
+        }
+        let token = token.to_string();
+        self.pos += 1;
+        return Some(token);
+    }
+
+    pub fn expect(&mut self, expected: &str) -> Result<(), String> {
+        let token = self.next()?;
+        if token != expected {
+            return Err(format!(
+                "Expected '{}' but found '{}'",
+                expected, token
+            ));
+        }
+        return Ok(());
+    }
+}
+
+struct Parser<'a, B: Builder> {
+    tokens: Tokens,
+    builder: &'a B,
+    depth: usize,
+}
+
+impl<'a, B: Builder> Parser<'a, B> {
+    fn parse_or(&mut self) -> Result<B::Filter, String> {
+        if self.builder.same_precedence() {
+            return self.parse_and_or();
+        }
+        let mut filter = self.parse_and()?;
+        while self.tokens.next_if(&["or", "||"]).is_some() {
+            filter = self.builder.or(filter, self.parse_and()?);
+        }
+        return Ok(filter);
+    }
+
+    fn parse_and(&mut self) -> Result<B::Filter, String> {
+        let mut filter = self.parse_not()?;
+        while self.tokens.next_if(&["and", "&&"]).is_some() {
+            filter = self.builder.and(filter, self.parse_not()?);
+        }
+        return Ok(filter);
+    }
+
+    /// Parses `and` and `or` with the same precedence, from left to right.
+    fn parse_and_or(&mut self) -> Result<B::Filter, String> {
+        let mut filter = self.parse_not()?;
+        loop {
+            if self.tokens.next_if(&["and", "&&"]).is_some() {
+                filter = self.builder.and(filter, self.parse_not()?);
+            } else if self.tokens.next_if(&["or", "||"]).is_some() {
+                filter = self.builder.or(filter, self.parse_not()?);
+            } else {
+                return Ok(filter);
+            }
+        }
+    }
+
+    fn parse_not(&mut self) -> Result<B::Filter, String> {
+        if self.tokens.next_if(&["not", "!"]).is_some() {
+            self.enter()?;
+            let filter = self.builder.not(self.parse_not()?);
+            self.depth -= 1;
+            return Ok(filter);
+        }
+        return self.parse_primary();
+    }
+
+    fn parse_primary(&mut self) -> Result<B::Filter, String> {
+        let token = self.tokens.next()?;
+        if token == "(" {
+            self.enter()?;
+            let filter = self.parse_or()?;
+            if self.tokens.next()? != ")" {
+                return Err("Expected ')'".to_string());
+            }
+            self.depth -= 1;
+            return Ok(filter);
+        }
+        if token == ")" {
+            return Err("Unexpected ')'".to_string());
+        }
+        return self.builder.primitive(&token, &mut self.tokens);
+    }
+
+    fn enter(&mut self) -> Result<(), String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(format!("Nested more than {} levels", MAX_DEPTH));
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes the expression fully parenthesized, with single-word primitives.
+    struct Printer {
+        same_precedence: bool,
+    }
+
+    impl Builder for Printer {
+        type Filter = String;
+
+        fn primitive(
+            &self,
+            token: &str,
+            _tokens: &mut Tokens,
+        ) -> Result<String, String> {
+            if !token.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return Err(format!("Unknown primitive '{}'", token));
+            }
+            return Ok(token.to_string());
+        }
+
+        fn and(&self, f1: String, f2: String) -> String {
+            return format!("({} and {})", f1, f2);
+        }
+
+        fn or(&self, f1: String, f2: String) -> String {
+            return format!("({} or {})", f1, f2);
+        }
+
+        fn not(&self, f: String) -> String {
+            return format!("not {}", f);
+        }
+
+        fn same_precedence(&self) -> bool {
+            return self.same_precedence;
+        }
+    }
+
+    fn print(expr: &str) -> Result<String, String> {
+        let printer = Printer {
+            same_precedence: false,
+        };
+        return parse(expr, &printer);
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and_tighter_than_or() {
+        assert_eq!(print("a or b and c").unwrap(), "(a or (b and c))");
+        assert_eq!(print("a and b or c").unwrap(), "((a and b) or c)");
+        assert_eq!(print("not a and b").unwrap(), "(not a and b)");
+        assert_eq!(print("a or b or c").unwrap(), "((a or b) or c)");
+        assert_eq!(print("(a or b) and c").unwrap(), "((a or b) and c)");
+        assert_eq!(print("not (a or b)").unwrap(), "not (a or b)");
+        assert_eq!(print("not not a").unwrap(), "not not a");
+    }
+
+    #[test]
+    fn groups_left_to_right_with_same_precedence() {
+        let printer = Printer {
+            same_precedence: true,
+        };
+        let print = |expr| parse(expr, &printer).unwrap();
+        assert_eq!(print("a or b and c"), "((a or b) and c)");
+        assert_eq!(print("a and b or c"), "((a and b) or c)");
+        assert_eq!(print("a or (b and c)"), "(a or (b and c))");
+        assert_eq!(print("not a or b && c"), "((not a or b) and c)");
+    }
+
+    #[test]
+    fn splits_symbols_without_spaces() {
+        assert_eq!(print("!a&&b||c").unwrap(), "((not a and b) or c)");
+        assert_eq!(print("!(a||b)").unwrap(), "not (a or b)");
+        assert_eq!(print("not(a)").unwrap(), "not a");
+        assert_eq!(print("((a))").unwrap(), "a");
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        assert_eq!(print("").unwrap_err(), "Invalid filter '': Unexpected end");
+        assert_eq!(
+            print("a b").unwrap_err(),
+            "Invalid filter 'a b': Unexpected 'b'"
+        );
+        assert_eq!(
+            print("a)").unwrap_err(),
+            "Invalid filter 'a)': Unexpected ')'"
+        );
+        assert_eq!(
+            print("(a b)").unwrap_err(),
+            "Invalid filter '(a b)': Expected ')'"
+        );
+        assert_eq!(
+            print(")").unwrap_err(),
+            "Invalid filter ')': Unexpected ')'"
+        );
+        assert!(print("(a").is_err());
+        assert!(print("a and").is_err());
+        assert!(print("a or or b").is_err());
+        assert!(print("!").is_err());
+        assert!(print("a & b").is_err());
+    }
+
+    #[test]
+    fn limits_nesting() {
+        let nested = |depth: usize| {
+            format!("{}a{}", "(".repeat(depth), ")".repeat(depth))
+        };
+        assert!(print(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            print(&nested(MAX_DEPTH + 1)).unwrap_err(),
+            format!(
+                "Invalid filter '{}': Nested more than {} levels",
+                nested(MAX_DEPTH + 1),
+                MAX_DEPTH
+            )
+        );
+        assert!(print(&format!("{}a", "!".repeat(MAX_DEPTH))).is_ok());
+        assert!(print(&format!("{}a", "!".repeat(100_000))).is_err());
+    }
+}
@@ -1,19 +1,52 @@
-use crate::args;
-use sysctl::Ctl;
-use sysctl::Sysctl;
+use crate::args::{self, forward::Family};
+use pnet::datalink;
 
 use log::info;
+use std::fs;
 
 pub fn main_forward(args: args::forward::Arguments) -> Result<(), String> {
+    let iface_name = args.iface.as_ref().map(|iface| iface.name.as_str());
+
+    if let Some(file) = &args.save {
+        let mut names = profile_names(iface_name);
+        for name in forwarding_names(&args.families, iface_name) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        let snapshot = Sysctls::read(&names)?;
+        fs::write(file, snapshot.to_string())
+            .map_err(|e| format!("Error saving sysctls to {}: {}", file, e))?;
+        info!("Saved sysctls to {}", file);
+    }
+
+    if let Some(file) = &args.restore {
+        let content = fs::read_to_string(file)
+            .map_err(|e| format!("Error reading sysctls {}: {}", file, e))?;
+        let snapshot = Sysctls::parse(&content)
+            .map_err(|e| format!("Error parsing sysctls {}: {}", file, e))?;
+        snapshot.apply()?;
+        log_sysctls(&snapshot);
+        return Ok(());
+    }
+
+    if args.mitm {
+        // required by the args
+        let iface_name = iface_name.unwrap();
+        let profile = mitm_profile(iface_name);
+        profile.apply()?;
+        log_sysctls(&profile);
+        return Ok(());
+    }
+
     match args.enable {
-        None => println!("{}", get_ip_forward()?),
+        None if args.save.is_some() => {}
+        None => print_state(&args.families, iface_name),
         Some(enable) => {
-            if enable {
-                enable_ip_forward()?;
-                info!("{} = {}", CTL_FORWARD, get_ip_forward()?);
-            } else {
-                disable_ip_forward()?;
-                info!("{} = {}", CTL_FORWARD, get_ip_forward()?);
+            let value = if enable { "1" } else { "0" };
+            for name in forwarding_names(&args.families, iface_name) {
+                set_sysctl(&name, value)?;
+                info!("{} = {}", name, get_sysctl(&name)?);
             }
         }
     }
@@ -21,29 +54,309 @@ pub fn main_forward(args: args::forward::Arguments) -> Result<(), String> {
     return Ok(());
 }
 
-const CTL_FORWARD: &str = "net.ipv4.ip_forward";
+fn log_sysctls(sysctls: &Sysctls) {
+    for (name, value) in sysctls.iter() {
+        info!("{} = {}", name, value);
+    }
+}
+
+/// Prints a table with the forwarding state of the families, globally and for
+/// the interface, or for every interface if none is given. The values that
+/// don't exist (like IPv6 ones when it is disabled) are shown as "-".
+fn print_state(families: &[Family], iface_name: Option<&str>) {
+    let mut ifaces_names = vec![None];
+    match iface_name {
+        Some(iface_name) => ifaces_names.push(Some(iface_name.to_string())),
+        None => ifaces_names.extend(
+            datalink::interfaces()
+                .into_iter()
+                .map(|iface| Some(iface.name)),
+        ),
+    }
+
+    println!(
+        "FAMILY IFACE            FORWARDING SEND_REDIRECTS RP_FILTER PROXY_ARP"
+    );
+    for family in families.iter() {
+        for iface_name in ifaces_names.iter() {
+            let iface_name = iface_name.as_deref();
+            let conf = |key: &str| match family {
+                Family::Ipv4 => get_sysctl(&format!(
+                    "net.ipv4.conf.{}.{}",
+                    iface_name.unwrap_or("all"),
+                    key
+                ))
+                .unwrap_or_else(|_| "-".to_string()),
+                Family::Ipv6 => "-".to_string(),
+            };
+            println!(
+                "{:<6} {:<16} {:<10} {:<14} {:<9} {}",
+                family_name(*family),
+                iface_name.unwrap_or("all"),
+                get_sysctl(&forwarding_name(*family, iface_name))
+                    .unwrap_or_else(|_| "-".to_string()),
+                conf("send_redirects"),
+                conf("rp_filter"),
+                conf("proxy_arp"),
+            );
+        }
+    }
+}
+
+fn family_name(family: Family) -> &'static str {
+    return match family {
+        Family::Ipv4 => "ipv4",
+        Family::Ipv6 => "ipv6",
+    };
+}
 
-pub fn get_ip_forward() -> Result<String, String> {
-    let ctl = Ctl::new(CTL_FORWARD).unwrap();
-    let value = ctl
-        .value_string()
-        .map_err(|e| format!("Error retrieving IP forward value: {}", e))?;
+/// Name of the forwarding sysctl of the family, the global one or the one of
+/// the interface.
+pub fn forwarding_name(family: Family, iface_name: Option<&str>) -> String {
+    return match (family, iface_name) {
+        (Family::Ipv4, None) => CTL_FORWARD.to_string(),
+        (Family::Ipv4, Some(iface_name)) => {
+            format!("net.ipv4.conf.{}.forwarding", iface_name)
+        }
+        (Family::Ipv6, None) => "net.ipv6.conf.all.forwarding".to_string(),
+        (Family::Ipv6, Some(iface_name)) => {
+            format!("net.ipv6.conf.{}.forwarding", iface_name)
+        }
+    };
+}
 
-    return Ok(value);
+/// Forwarding sysctls of the families, the global ones and, if an interface is
+/// given, the ones of the interface.
+pub fn forwarding_names(
+    families: &[Family],
+    iface_name: Option<&str>,
+) -> Vec<String> {
+    let mut names = Vec::new();
+    for family in families.iter() {
+        names.push(forwarding_name(*family, None));
+        if iface_name.is_some() {
+            names.push(forwarding_name(*family, iface_name));
+        }
+    }
+    return names;
 }
 
-pub fn set_ip_forward(value: &str) -> Result<(), String> {
-    let ctl = Ctl::new(CTL_FORWARD).unwrap();
-    ctl.set_value_string(value)
-        .map_err(|e| format!(": {}", e))?;
+pub const CTL_FORWARD: &str = "net.ipv4.ip_forward";
+
+/// Values of a set of sysctls, which can be applied together. They are
+/// serialized as lines of name and value.
+#[derive(Clone)]
+pub struct Sysctls {
+    values: Vec<(String, String)>,
+}
+
+impl Sysctls {
+    /// Takes the current values of the sysctls.
+    pub fn read(names: &[String]) -> Result<Self, String> {
+        let mut values = Vec::new();
+        for name in names.iter() {
+            values.push((name.clone(), get_sysctl(name)?));
+        }
+        return Ok(Self { values });
+    }
+
+    pub fn apply(&self) -> Result<(), String> {
+        for (name, value) in self.values.iter() {
+            set_sysctl(name, value)?;
+        }
+        return Ok(());
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(String, String)> {
+        return self.values.iter();
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut values = Vec::new();
+        for line in content.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                [] => {}
+                [name, value] => {
+                    values.push((name.to_string(), value.to_string()))
+                }
+                _ => return Err(format!("Invalid line: {}", line)),
+            }
+        }
+        return Ok(Self { values });
+    }
+}
+
+impl std::fmt::Display for Sysctls {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (name, value) in self.values.iter() {
+            writeln!(f, "{} {}", name, value)?;
+        }
+        return Ok(());
+    }
+}
+
+/// Sysctls involved in the forwarding of the intercepted traffic, the global
+/// ones and, if an interface is given, the ones of the interface.
+pub fn profile_names(iface_name: Option<&str>) -> Vec<String> {
+    let mut names = vec![
+        CTL_FORWARD.to_string(),
+        "net.ipv4.conf.all.send_redirects".to_string(),
+        "net.ipv4.conf.all.rp_filter".to_string(),
+        "net.ipv4.conf.all.proxy_arp".to_string(),
+    ];
+    if let Some(iface_name) = iface_name {
+        names.push(format!("net.ipv4.conf.{}.send_redirects", iface_name));
+        names.push(format!("net.ipv4.conf.{}.rp_filter", iface_name));
+        names.push(format!("net.ipv4.conf.{}.proxy_arp", iface_name));
+    }
+    return names;
+}
+
+/// Profile to forward the intercepted traffic through the interface: enables
+/// the forwarding, disables the ICMP redirects that reveal the attack (sent if
+/// any of all or the interface values is enabled), the reverse path filter
+/// that may drop the forwarded packets (the max of both values is used) and
+/// the proxy ARP, which would answer for other hosts (enabled if any of all or
+/// the interface values is).
+pub fn mitm_profile(iface_name: &str) -> Sysctls {
+    let values = vec![
+        (CTL_FORWARD.to_string(), "1".to_string()),
+        (
+            "net.ipv4.conf.all.send_redirects".to_string(),
+            "0".to_string(),
+        ),
+        ("net.ipv4.conf.all.rp_filter".to_string(), "0".to_string()),
+        ("net.ipv4.conf.all.proxy_arp".to_string(), "0".to_string()),
+        (
+            format!("net.ipv4.conf.{}.send_redirects", iface_name),
+            "0".to_string(),
+        ),
+        (
+            format!("net.ipv4.conf.{}.rp_filter", iface_name),
+            "0".to_string(),
+        ),
+        (
+            format!("net.ipv4.conf.{}.proxy_arp", iface_name),
+            "0".to_string(),
+        ),
+    ];
+    return Sysctls { values };
+}
+
+pub fn get_sysctl(name: &str) -> Result<String, String> {
+    let value = fs::read_to_string(sysctl_path(name))
+        .map_err(|e| format!("Error retrieving {}: {}", name, e))?;
+    return Ok(value.trim().to_string());
+}
+
+pub fn set_sysctl(name: &str, value: &str) -> Result<(), String> {
+    fs::write(sysctl_path(name), value)
+        .map_err(|e| format!("Error setting {}: {}", name, e))?;
     return Ok(());
 }
 
-pub fn enable_ip_forward() -> Result<(), String> {
-    set_ip_forward("1").map_err(|e| format!("Error enabling IP forward: {}", e))
+/// Prefixes of the sysctls of an interface, which are followed by the name of
+/// the interface and the key.
+const IFACE_PREFIXES: [&str; 4] = [
+    "net.ipv4.conf.",
+    "net.ipv6.conf.",
+    "net.ipv4.neigh.",
+    "net.ipv6.neigh.",
+];
+
+/// Path of the sysctl under /proc/sys. The dots of the name separate the
+/// directories, except the ones of interface names, like `eth0.100` for a
+/// VLAN, which are kept.
+fn sysctl_path(name: &str) -> String {
+    for prefix in IFACE_PREFIXES.iter() {
+        if let Some((iface_name, key)) = name
+            .strip_prefix(prefix)
+            .and_then(|iface_key| iface_key.rsplit_once('.'))
+        {
+            return format!(
+                "/proc/sys/{}/{}/{}",
+                prefix.trim_end_matches('.').replace('.', "/"),
+                iface_name,
+                key
+            );
+        }
+    }
+    return format!("/proc/sys/{}", name.replace('.', "/"));
 }
 
-pub fn disable_ip_forward() -> Result<(), String> {
-    set_ip_forward("0")
-        .map_err(|e| format!("Error disabling IP forward: {}", e))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sysctls_round_trip() {
+        let content = "net.ipv4.ip_forward 0\n\
+                       net.ipv4.conf.all.rp_filter 2\n";
+        let sysctls = Sysctls::parse(content).unwrap();
+        assert_eq!(
+            sysctls.iter().cloned().collect::<Vec<_>>(),
+            vec![
+                (CTL_FORWARD.to_string(), "0".to_string()),
+                ("net.ipv4.conf.all.rp_filter".to_string(), "2".to_string()),
+            ]
+        );
+        assert_eq!(sysctls.to_string(), content);
+    }
+
+    #[test]
+    fn sysctls_skip_blank_lines_and_reject_invalid_ones() {
+        let sysctls = Sysctls::parse("\nnet.ipv4.ip_forward 1\n\n").unwrap();
+        assert_eq!(sysctls.iter().count(), 1);
+
+        let err = Sysctls::parse("net.ipv4.ip_forward").err().unwrap();
+        assert_eq!(err, "Invalid line: net.ipv4.ip_forward");
+        assert!(Sysctls::parse("net.ipv4.ip_forward 1 2").is_err());
+    }
+
+    #[test]
+    fn mitm_profile_is_saved_by_profile_names() {
+        let names = profile_names(Some("eth0"));
+        let profile = mitm_profile("eth0");
+        for (name, _) in profile.iter() {
+            assert!(names.contains(name), "{} is not saved", name);
+        }
+        assert!(names.contains(&"net.ipv4.conf.all.proxy_arp".to_string()));
+        assert!(names.contains(&"net.ipv4.conf.eth0.proxy_arp".to_string()));
+    }
+
+    #[test]
+    fn keeps_dots_of_interface_names_in_paths() {
+        assert_eq!(sysctl_path(CTL_FORWARD), "/proc/sys/net/ipv4/ip_forward");
+        assert_eq!(
+            sysctl_path("net.ipv4.conf.all.rp_filter"),
+            "/proc/sys/net/ipv4/conf/all/rp_filter"
+        );
+        assert_eq!(
+            sysctl_path("net.ipv4.conf.eth0.100.rp_filter"),
+            "/proc/sys/net/ipv4/conf/eth0.100/rp_filter"
+        );
+        for name in profile_names(Some("eth0.100")).iter().skip(4) {
+            assert!(sysctl_path(name).contains("/eth0.100/"), "{}", name);
+        }
+    }
+
+    #[test]
+    fn forwarding_paths_keep_dotted_interface_names() {
+        let path = |family, iface_name| {
+            return sysctl_path(&forwarding_name(family, iface_name));
+        };
+        assert_eq!(
+            path(Family::Ipv4, Some("eth0.100")),
+            "/proc/sys/net/ipv4/conf/eth0.100/forwarding"
+        );
+        assert_eq!(
+            path(Family::Ipv6, Some("eth0.100")),
+            "/proc/sys/net/ipv6/conf/eth0.100/forwarding"
+        );
+        assert_eq!(
+            path(Family::Ipv6, None),
+            "/proc/sys/net/ipv6/conf/all/forwarding"
+        );
+    }
 }
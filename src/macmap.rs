@@ -0,0 +1,174 @@
+use crate::validators::{self, IpRange};
+use pnet::ipnetwork::Ipv4Network;
+use pnet::util::MacAddr;
+use std::collections::HashMap;
+use std::fs;
+use std::net::Ipv4Addr;
+
+/// Table to pick the MAC to answer for each IP. It is loaded from a file with
+/// lines of IP (or network, or first-last range) and MAC, and an optional
+/// default for the rest of IPs:
+///
+/// ```text
+/// # comment
+/// 192.168.1.10 52:54:00:11:22:33
+/// 192.168.1.0/28 52:54:00:aa:bb:cc
+/// 192.168.1.20-192.168.1.30 52:54:00:dd:ee:ff
+/// * 52:54:00:00:00:01
+/// ```
+///
+/// Single IPs take precedence, then the networks and ranges in the order of the
+/// file, and then the default.
+pub struct MacMap {
+    ips: HashMap<Ipv4Addr, MacAddr>,
+    ranges: Vec<(IpRange, MacAddr)>,
+    default: Option<MacAddr>,
+}
+
+impl MacMap {
+    /// Creates a table that answers every IP with the same MAC.
+    pub fn single(mac: MacAddr) -> Self {
+        return Self {
+            ips: HashMap::new(),
+            ranges: Vec::new(),
+            default: Some(mac),
+        };
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Error reading MAC map {}: {}", path, e))?;
+        return Self::parse(&content)
+            .map_err(|e| format!("Error parsing MAC map {}: {}", path, e));
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut map = Self {
+            ips: HashMap::new(),
+            ranges: Vec::new(),
+            default: None,
+        };
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (ips, mac) = match fields.as_slice() {
+                [ips, mac] => (*ips, *mac),
+                _ => return Err(format!("Invalid line: {}", line)),
+            };
+            let mac = mac
+                .parse::<MacAddr>()
+                .map_err(|_| format!("Invalid MAC '{}'", mac))?;
+
+            if ips == "*" {
+                map.default = Some(mac);
+            } else if let Ok(ip) = ips.parse::<Ipv4Addr>() {
+                map.ips.insert(ip, mac);
+            } else if let Ok(net) = ips.parse::<Ipv4Network>() {
+                map.ranges.push(((net.network(), net.broadcast()), mac));
+            } else {
+                let range = validators::parse_ip_range(ips)
+                    .ok_or_else(|| format!("Invalid IP '{}'", ips))?;
+                map.ranges.push((range, mac));
+            }
+        }
+
+        return Ok(map);
+    }
+
+    /// MAC to answer for the IP, if any.
+    pub fn lookup(&self, ip: Ipv4Addr) -> Option<MacAddr> {
+        if let Some(mac) = self.ips.get(&ip) {
+            return Some(*mac);
+        }
+        for ((first, last), mac) in self.ranges.iter() {
+            if *first <= ip && ip <= *last {
+                return Some(*mac);
+            }
+        }
+        return self.default;
+    }
+
+    /// Whether the MAC is used to answer any IP.
+    pub fn contains_mac(&self, mac: MacAddr) -> bool {
+        return self.default == Some(mac)
+            || self.ips.values().any(|m| *m == mac)
+            || self.ranges.iter().any(|(_, m)| *m == mac);
+    }
+
+    pub fn entries_count(&self) -> usize {
+        return self.ips.len()
+            + self.ranges.len()
+            + self.default.iter().count();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mac(last: u8) -> MacAddr {
+        return MacAddr::new(0x52, 0x54, 0, 0, 0, last);
+    }
+
+    #[test]
+    fn looks_up_ips_then_ranges_then_default() {
+        let map = MacMap::parse(
+            "# comment\n\
+             \n\
+             192.168.1.0/28 52:54:00:00:00:01\n\
+             192.168.1.5-192.168.1.20 52:54:00:00:00:02\n\
+             192.168.1.10 52:54:00:00:00:03\n\
+             * 52:54:00:00:00:04\n",
+        )
+        .unwrap();
+
+        assert_eq!(map.entries_count(), 4);
+        // the single IP wins over the network and range that contain it
+        assert_eq!(map.lookup("192.168.1.10".parse().unwrap()), Some(mac(3)));
+        // the network comes first in the file
+        assert_eq!(map.lookup("192.168.1.5".parse().unwrap()), Some(mac(1)));
+        assert_eq!(map.lookup("192.168.1.16".parse().unwrap()), Some(mac(2)));
+        assert_eq!(map.lookup("192.168.1.20".parse().unwrap()), Some(mac(2)));
+        assert_eq!(map.lookup("192.168.1.21".parse().unwrap()), Some(mac(4)));
+        assert!(map.contains_mac(mac(2)));
+        assert!(!map.contains_mac(mac(5)));
+    }
+
+    #[test]
+    fn answers_nothing_without_default() {
+        let map = MacMap::parse("10.0.0.1 52:54:00:00:00:01").unwrap();
+        assert_eq!(map.lookup("10.0.0.1".parse().unwrap()), Some(mac(1)));
+        assert_eq!(map.lookup("10.0.0.2".parse().unwrap()), None);
+
+        let map = MacMap::single(mac(1));
+        assert_eq!(map.lookup("10.0.0.2".parse().unwrap()), Some(mac(1)));
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        assert_eq!(
+            MacMap::parse("10.0.0.1").err().unwrap(),
+            "Invalid line: 10.0.0.1"
+        );
+        assert_eq!(
+            MacMap::parse("10.0.0.1 52:54:00:00:00:01 x").err().unwrap(),
+            "Invalid line: 10.0.0.1 52:54:00:00:00:01 x"
+        );
+        assert_eq!(
+            MacMap::parse("10.0.0.1 52:54:00").err().unwrap(),
+            "Invalid MAC '52:54:00'"
+        );
+        assert_eq!(
+            MacMap::parse("10.0.0.9-10.0.0.1 52:54:00:00:00:01")
+                .err()
+                .unwrap(),
+            "Invalid IP '10.0.0.9-10.0.0.1'"
+        );
+        assert!(MacMap::parse("10.0.0.0/33 52:54:00:00:00:01").is_err());
+    }
+}
@@ -1,14 +1,28 @@
+#![allow(clippy::needless_return)]
+
 mod args;
 mod arp;
+mod conflict;
+mod expr;
+mod ping;
+mod recover;
 mod reply;
+mod route;
 mod scan;
+mod scanner;
+mod sniff;
 mod spoof;
+mod tarpit;
 mod validators;
 mod forward;
+mod macmap;
+mod neigh;
+mod oui;
+mod pcap;
+mod output;
 
 use args::Arguments;
 use log::error;
-use stderrlog;
 
 pub fn init_log(verbosity: usize) {
     stderrlog::new()
@@ -22,6 +36,18 @@ fn main() {
     let args = Arguments::parse_args();
 
     let res = match args {
+        Arguments::Conflict(args) => {
+            init_log(args.verbosity);
+            conflict::main_conflict(args)
+        }
+        Arguments::Ping(args) => {
+            init_log(args.verbosity);
+            ping::main_ping(args)
+        }
+        Arguments::Recover(args) => {
+            init_log(args.verbosity);
+            recover::main_recover(args)
+        }
         Arguments::Reply(args) => {
             init_log(args.verbosity);
             reply::main_reply(args)
@@ -30,6 +56,10 @@ fn main() {
             init_log(args.verbosity);
             scan::main_scan(args)
         }
+        Arguments::Sniff(args) => {
+            init_log(args.verbosity);
+            sniff::main_sniff(args)
+        }
         Arguments::Spoof(args) => {
             init_log(args.verbosity);
             spoof::main_spoof(args)
@@ -0,0 +1,83 @@
+use pnet::util::MacAddr;
+use std::collections::HashMap;
+use std::fs;
+use std::net::Ipv4Addr;
+
+const ARP_FILE: &str = "/proc/net/arp";
+
+/// Entry with a resolved MAC. The kernel only shows the hardware address of
+/// the entries in REACHABLE, STALE, DELAY or PROBE states with this flag.
+const ATF_COM: u32 = 0x02;
+
+/// Returns the resolved entries of the kernel neighbor cache for the
+/// interface.
+pub fn get_arp_cache(
+    iface_name: &str,
+) -> Result<HashMap<Ipv4Addr, MacAddr>, String> {
+    let content = fs::read_to_string(ARP_FILE)
+        .map_err(|e| format!("Error reading {}: {}", ARP_FILE, e))?;
+    return Ok(parse_arp_cache(&content, iface_name));
+}
+
+/// Parses the neighbor cache, in the format of the ARP file.
+fn parse_arp_cache(
+    content: &str,
+    iface_name: &str,
+) -> HashMap<Ipv4Addr, MacAddr> {
+    let mut cache = HashMap::new();
+
+    // IP address, HW type, Flags, HW address, Mask, Device
+    for line in content.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 6 || fields[5] != iface_name {
+            continue;
+        }
+
+        let flags =
+            match u32::from_str_radix(fields[2].trim_start_matches("0x"), 16) {
+                Ok(flags) => flags,
+                Err(_) => continue,
+            };
+        if flags & ATF_COM == 0 {
+            continue;
+        }
+
+        let ip = fields[0].parse::<Ipv4Addr>();
+        let mac = fields[3].parse::<MacAddr>();
+        if let (Ok(ip), Ok(mac)) = (ip, mac) {
+            if mac != MacAddr::zero() {
+                cache.insert(ip, mac);
+            }
+        }
+    }
+
+    return cache;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARP: &str = "\
+IP address       HW type     Flags       HW address            Mask     Device
+192.168.100.1    0x1         0x2         52:54:00:00:00:01     *        eth0
+192.168.100.7    0x1         0x0         00:00:00:00:00:00     *        eth0
+192.168.100.9    0x1         0x6         52:54:00:00:00:09     *        eth0
+192.168.1.1      0x1         0x2         52:54:00:00:01:01     *        wlan0
+";
+
+    #[test]
+    fn keeps_resolved_entries_of_the_interface() {
+        let cache = parse_arp_cache(ARP, "eth0");
+        assert_eq!(cache.len(), 2);
+        assert_eq!(
+            cache.get(&Ipv4Addr::new(192, 168, 100, 1)),
+            Some(&MacAddr::new(0x52, 0x54, 0, 0, 0, 1))
+        );
+        assert_eq!(
+            cache.get(&Ipv4Addr::new(192, 168, 100, 9)),
+            Some(&MacAddr::new(0x52, 0x54, 0, 0, 0, 9))
+        );
+        assert!(parse_arp_cache(ARP, "eth1").is_empty());
+    }
+}
@@ -0,0 +1,33 @@
+// This module is also included by the build script to generate the embedded
+// table, so it can only depend on std.
+
+/// Parses a line with a prefix followed by the vendor name. The prefix can be
+/// written as "00:00:0C", "00-00-0C" or "00000C", and the IEEE "(hex)" marker
+/// between the prefix and the vendor is skipped.
+pub fn parse_oui_line(line: &str) -> Option<([u8; 3], String)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let mut parts = line.splitn(2, char::is_whitespace);
+    let prefix = parts.next()?;
+    let vendor = parts.next()?.trim();
+    let vendor = vendor.strip_prefix("(hex)").unwrap_or(vendor).trim();
+    if vendor.is_empty() || vendor.starts_with('(') {
+        return None;
+    }
+
+    let digits: String =
+        prefix.chars().filter(|c| *c != ':' && *c != '-').collect();
+    if digits.len() != 6 || !digits.is_ascii() {
+        return None;
+    }
+
+    let mut oui = [0u8; 3];
+    for (i, byte) in oui.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).ok()?;
+    }
+
+    return Some((oui, vendor.to_string()));
+}
@@ -0,0 +1,193 @@
+mod line;
+
+use line::parse_oui_line;
+use pnet::util::MacAddr;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+
+/// Prefixes of data/oui.txt, sorted by the build script so they can be
+/// binary searched without parsing the registry at startup.
+static EMBEDDED_OUIS: &[([u8; 3], &str)] =
+    include!(concat!(env!("OUT_DIR"), "/oui_table.rs"));
+
+/// Database to resolve the vendor of a MAC address from its OUI (the first 24
+/// bits).
+pub struct OuiDb {
+    /// Prefixes loaded from files, which override the embedded ones.
+    vendors: HashMap<[u8; 3], String>,
+}
+
+impl OuiDb {
+    /// Creates the database with the embedded prefixes.
+    pub fn embedded() -> Self {
+        return Self {
+            vendors: HashMap::new(),
+        };
+    }
+
+    /// Creates the database with the embedded prefixes, overridden by the
+    /// ones of the given file, if any.
+    pub fn new(override_file: Option<&str>) -> Result<Self, String> {
+        let mut db = Self::embedded();
+        if let Some(path) = override_file {
+            db.load_file(path)?;
+        }
+        return Ok(db);
+    }
+
+    pub fn load_file(&mut self, path: &str) -> Result<(), String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Error reading OUI file {}: {}", path, e))?;
+        self.load_str(&content);
+        return Ok(());
+    }
+
+    fn load_str(&mut self, content: &str) {
+        for line in content.lines() {
+            if let Some((oui, vendor)) = parse_oui_line(line) {
+                self.vendors.insert(oui, vendor);
+            }
+        }
+    }
+
+    pub fn lookup(&self, mac: MacAddr) -> Option<&str> {
+        let oui = [mac.0, mac.1, mac.2];
+        if let Some(vendor) = self.vendors.get(&oui) {
+            return Some(vendor);
+        }
+
+        return EMBEDDED_OUIS
+            .binary_search_by_key(&oui, |(prefix, _)| *prefix)
+            .ok()
+            .map(|i| EMBEDDED_OUIS[i].1);
+    }
+
+    pub fn describe(&self, mac: MacAddr) -> MacInfo {
+        return MacInfo {
+            vendor: self.lookup(mac).map(|v| v.to_string()),
+            local: is_locally_administered(mac),
+            multicast: is_multicast(mac),
+        };
+    }
+}
+
+/// Vendor and address type of a MAC address.
+pub struct MacInfo {
+    pub vendor: Option<String>,
+    pub local: bool,
+    pub multicast: bool,
+}
+
+impl fmt::Display for MacInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(vendor) = &self.vendor {
+            parts.push(vendor.as_str());
+        }
+        if self.local {
+            parts.push("locally administered");
+        }
+        if self.multicast {
+            parts.push("multicast");
+        }
+        if parts.is_empty() {
+            parts.push("unknown vendor");
+        }
+        return write!(f, "{}", parts.join(", "));
+    }
+}
+
+pub fn is_locally_administered(mac: MacAddr) -> bool {
+    return mac.0 & 0x02 != 0;
+}
+
+pub fn is_multicast(mac: MacAddr) -> bool {
+    return mac.0 & 0x01 != 0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_prefix_formats() {
+        let cisco = Some(([0x00, 0x00, 0x0c], "Cisco".to_string()));
+        assert_eq!(parse_oui_line("00:00:0C\tCisco"), cisco);
+        assert_eq!(parse_oui_line("00-00-0c Cisco"), cisco);
+        assert_eq!(parse_oui_line("00000C   Cisco"), cisco);
+        assert_eq!(parse_oui_line("00-00-0C   (hex)\t\tCisco"), cisco);
+    }
+
+    #[test]
+    fn skips_non_prefix_lines() {
+        assert_eq!(parse_oui_line("# 00:00:0C Cisco"), None);
+        assert_eq!(parse_oui_line(""), None);
+        assert_eq!(parse_oui_line("00000C     (base 16)\t\tCisco"), None);
+        assert_eq!(parse_oui_line("00:00:0C"), None);
+        assert_eq!(parse_oui_line("00:00:0G Bad"), None);
+        assert_eq!(parse_oui_line("170 West Tasman Dr."), None);
+        assert_eq!(parse_oui_line("00:00:0C:01 Too long"), None);
+    }
+
+    #[test]
+    fn embedded_table_is_sorted() {
+        assert!(EMBEDDED_OUIS.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn looks_up_embedded_vendor() {
+        let db = OuiDb::embedded();
+        let mac = MacAddr::new(0x00, 0x0c, 0x29, 0x12, 0x34, 0x56);
+        assert_eq!(db.lookup(mac), Some("VMware, Inc."));
+        assert_eq!(db.lookup(MacAddr::new(0x02, 0xff, 0xff, 0, 0, 1)), None);
+    }
+
+    #[test]
+    fn loaded_prefixes_override_embedded() {
+        let mut db = OuiDb::embedded();
+        db.load_str("00:0C:29 My VMs\n02-FF-FF Lab");
+        let vm = MacAddr::new(0x00, 0x0c, 0x29, 0x12, 0x34, 0x56);
+        assert_eq!(db.lookup(vm), Some("My VMs"));
+        assert_eq!(
+            db.lookup(MacAddr::new(0x02, 0xff, 0xff, 0, 0, 1)),
+            Some("Lab")
+        );
+    }
+
+    #[test]
+    fn flags_local_and_multicast() {
+        let local = MacAddr::new(0x52, 0x54, 0x00, 0, 0, 1);
+        assert!(is_locally_administered(local));
+        assert!(!is_multicast(local));
+
+        let multicast = MacAddr::new(0x01, 0x00, 0x5e, 0, 0, 1);
+        assert!(!is_locally_administered(multicast));
+        assert!(is_multicast(multicast));
+
+        let broadcast = MacAddr::broadcast();
+        assert!(is_locally_administered(broadcast));
+        assert!(is_multicast(broadcast));
+    }
+
+    #[test]
+    fn describes_mac() {
+        let db = OuiDb::embedded();
+        let vm = MacAddr::new(0x00, 0x0c, 0x29, 0x12, 0x34, 0x56);
+        assert_eq!(db.describe(vm).to_string(), "VMware, Inc.");
+
+        // locally administered addresses are never assigned to vendors
+        let local = MacAddr::new(0x02, 0xff, 0xff, 0, 0, 1);
+        assert_eq!(db.describe(local).to_string(), "locally administered");
+
+        let broadcast = db.describe(MacAddr::broadcast()).to_string();
+        assert_eq!(broadcast, "locally administered, multicast");
+
+        let unknown = MacInfo {
+            vendor: None,
+            local: false,
+            multicast: false,
+        };
+        assert_eq!(unknown.to_string(), "unknown vendor");
+    }
+}
@@ -0,0 +1,208 @@
+use crate::args::scan::OutputFormat;
+use crate::oui::MacInfo;
+use pnet::util::MacAddr;
+use std::net::Ipv4Addr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const CSV_HEADER: &str = "record,ip,mac,vendor,local,multicast,gateway,iface,rtt_ms,tries,timestamp,sent,hosts,retried,timed_out";
+
+pub struct HostRecord {
+    pub ip: Ipv4Addr,
+    pub mac: MacAddr,
+    pub mac_info: MacInfo,
+    pub gateway: bool,
+    pub iface: String,
+    pub rtt: Duration,
+    pub tries: u32,
+    pub timestamp: SystemTime,
+}
+
+pub struct SummaryRecord {
+    pub iface: String,
+    pub sent: u64,
+    pub hosts: u64,
+    pub retried: u64,
+    pub timed_out: u64,
+    pub timestamp: SystemTime,
+}
+
+/// Prints the scan results in the selected format. The plain format prints
+/// the hosts as they are discovered, while the grep one sorts them by IP
+/// before printing, so they are retained until the end of the scan. Every
+/// format ends with a summary record.
+pub struct ScanPrinter {
+    format: OutputFormat,
+    hosts: Vec<HostRecord>,
+}
+
+impl ScanPrinter {
+    pub fn new(format: OutputFormat) -> Self {
+        if let OutputFormat::Csv = format {
+            println!("{}", CSV_HEADER);
+        }
+
+        return Self {
+            format,
+            hosts: Vec::new(),
+        };
+    }
+
+    pub fn print_host(&mut self, host: HostRecord) {
+        match self.format {
+            OutputFormat::Plain => println!("{}", host_plain(&host)),
+            OutputFormat::Json => println!("{}", host_json(&host)),
+            OutputFormat::Csv => println!("{}", host_csv(&host)),
+            OutputFormat::Grep => self.hosts.push(host),
+        }
+    }
+
+    pub fn finish(mut self, summary: SummaryRecord) {
+        match self.format {
+            OutputFormat::Plain => println!("{}", summary_comment(&summary)),
+            OutputFormat::Json => println!("{}", summary_json(&summary)),
+            OutputFormat::Csv => println!("{}", summary_csv(&summary)),
+            OutputFormat::Grep => {
+                self.hosts.sort_by_key(|host| host.ip);
+                for host in self.hosts.iter() {
+                    println!("{}", host_grep(host));
+                }
+                println!("{}", summary_comment(&summary));
+            }
+        }
+    }
+}
+
+fn host_plain(host: &HostRecord) -> String {
+    return format!(
+        "{} {} {}{}",
+        host.ip,
+        host.mac,
+        host.mac_info,
+        if host.gateway { " [gateway]" } else { "" }
+    );
+}
+
+fn host_json(host: &HostRecord) -> String {
+    return format!(
+        "{{\"record\":\"host\",\"ip\":\"{}\",\"mac\":\"{}\",\"vendor\":{},\"local\":{},\"multicast\":{},\"gateway\":{},\"iface\":{},\"rtt_ms\":{},\"tries\":{},\"timestamp\":{}}}",
+        host.ip,
+        host.mac,
+        match &host.mac_info.vendor {
+            Some(vendor) => json_string(vendor),
+            None => "null".to_string(),
+        },
+        host.mac_info.local,
+        host.mac_info.multicast,
+        host.gateway,
+        json_string(&host.iface),
+        format_millis(host.rtt),
+        host.tries,
+        format_timestamp(host.timestamp),
+    );
+}
+
+fn summary_json(summary: &SummaryRecord) -> String {
+    return format!(
+        "{{\"record\":\"summary\",\"iface\":{},\"sent\":{},\"hosts\":{},\"retried\":{},\"timed_out\":{},\"timestamp\":{}}}",
+        json_string(&summary.iface),
+        summary.sent,
+        summary.hosts,
+        summary.retried,
+        summary.timed_out,
+        format_timestamp(summary.timestamp),
+    );
+}
+
+fn host_csv(host: &HostRecord) -> String {
+    return format!(
+        "host,{},{},{},{},{},{},{},{},{},{},,,,",
+        host.ip,
+        host.mac,
+        csv_string(host.mac_info.vendor.as_deref().unwrap_or("")),
+        host.mac_info.local,
+        host.mac_info.multicast,
+        host.gateway,
+        csv_string(&host.iface),
+        format_millis(host.rtt),
+        host.tries,
+        format_timestamp(host.timestamp),
+    );
+}
+
+fn summary_csv(summary: &SummaryRecord) -> String {
+    return format!(
+        "summary,,,,,,,{},,,{},{},{},{},{}",
+        csv_string(&summary.iface),
+        format_timestamp(summary.timestamp),
+        summary.sent,
+        summary.hosts,
+        summary.retried,
+        summary.timed_out,
+    );
+}
+
+fn host_grep(host: &HostRecord) -> String {
+    return format!(
+        "{} {} {} {} {} {} {} {}",
+        host.ip,
+        host.mac,
+        host.iface,
+        format_millis(host.rtt),
+        host.tries,
+        if host.gateway { "gateway" } else { "-" },
+        format_timestamp(host.timestamp),
+        host.mac_info,
+    );
+}
+
+/// Summary as a trailing `#` comment line, used by the plain and grep
+/// formats.
+fn summary_comment(summary: &SummaryRecord) -> String {
+    return format!(
+        "# {} sent={} hosts={} retried={} timed_out={} {}",
+        summary.iface,
+        summary.sent,
+        summary.hosts,
+        summary.retried,
+        summary.timed_out,
+        format_timestamp(summary.timestamp),
+    );
+}
+
+/// Seconds since the Unix epoch, with milliseconds.
+pub fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    return format!(
+        "{}.{:03}",
+        since_epoch.as_secs(),
+        since_epoch.subsec_millis()
+    );
+}
+
+fn format_millis(duration: Duration) -> String {
+    return format!("{:.3}", duration.as_secs_f64() * 1000.0);
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                escaped.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    return escaped;
+}
+
+fn csv_string(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        return format!("\"{}\"", s.replace('"', "\"\""));
+    }
+    return s.to_string();
+}
@@ -0,0 +1,381 @@
+use crate::expr::{self, Builder, Tokens};
+use pnet::{
+    ipnetwork::Ipv4Network,
+    packet::{
+        arp::ArpPacket,
+        ethernet::{EtherTypes, EthernetPacket},
+        ip::IpNextHeaderProtocols,
+        ipv4::Ipv4Packet,
+        tcp::TcpPacket,
+        udp::UdpPacket,
+        Packet,
+    },
+    util::MacAddr,
+};
+use std::net::Ipv4Addr;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Dir {
+    Src,
+    Dst,
+    Any,
+}
+
+#[derive(Debug)]
+pub enum Proto {
+    Arp,
+    Ip,
+    Ip6,
+    Tcp,
+    Udp,
+    Icmp,
+}
+
+/// Filter of frames with a subset of the BPF (tcpdump) syntax:
+///
+/// ```text
+/// [src|dst] host <ip>
+/// [src|dst] net <ip/prefix>
+/// [src|dst] port <port>
+/// ether [src|dst] host <mac>
+/// arp | ip | ip6 | tcp | udp | icmp [<host, net or port primitive>]
+/// ```
+///
+/// A protocol can be followed by a host, net or port primitive, like in
+/// `tcp dst port 80`, which matches both. The primitives can be combined with
+/// `and` (`&&`), `or` (`||`), `not` (`!`) and parentheses. As in tcpdump,
+/// `and` and `or` have the same precedence and are grouped from left to
+/// right, so `tcp or udp and port 53` is `(tcp or udp) and port 53`. Other
+/// tcpdump constructs, like `src or dst` or implicit repeated qualifiers
+/// (`host a or b`), are not supported.
+#[derive(Debug)]
+pub enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Host(Dir, Ipv4Addr),
+    Net(Dir, Ipv4Network),
+    Port(Dir, u16),
+    EtherHost(Dir, MacAddr),
+    Proto(Proto),
+}
+
+impl Filter {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        return expr::parse(expr, &FilterBuilder);
+    }
+
+    pub fn matches(&self, buf: &[u8]) -> bool {
+        let ether = match EthernetPacket::new(buf) {
+            Some(ether) => ether,
+            None => return false,
+        };
+        return self.matches_ether(&ether);
+    }
+
+    fn matches_ether(&self, ether: &EthernetPacket) -> bool {
+        match self {
+            Self::And(f1, f2) => {
+                f1.matches_ether(ether) && f2.matches_ether(ether)
+            }
+            Self::Or(f1, f2) => {
+                f1.matches_ether(ether) || f2.matches_ether(ether)
+            }
+            Self::Not(f) => !f.matches_ether(ether),
+            Self::EtherHost(dir, mac) => match_dir(
+                *dir,
+                ether.get_source() == *mac,
+                ether.get_destination() == *mac,
+            ),
+            Self::Host(dir, ip) => match ips(ether) {
+                Some((src, dst)) => match_dir(*dir, src == *ip, dst == *ip),
+                None => false,
+            },
+            Self::Net(dir, net) => match ips(ether) {
+                Some((src, dst)) => {
+                    match_dir(*dir, net.contains(src), net.contains(dst))
+                }
+                None => false,
+            },
+            Self::Port(dir, port) => match ports(ether) {
+                Some((src, dst)) => match_dir(*dir, src == *port, dst == *port),
+                None => false,
+            },
+            Self::Proto(proto) => match proto {
+                Proto::Arp => ether.get_ethertype() == EtherTypes::Arp,
+                Proto::Ip => ether.get_ethertype() == EtherTypes::Ipv4,
+                Proto::Ip6 => ether.get_ethertype() == EtherTypes::Ipv6,
+                Proto::Tcp => {
+                    ip_protocol(ether) == Some(IpNextHeaderProtocols::Tcp)
+                }
+                Proto::Udp => {
+                    ip_protocol(ether) == Some(IpNextHeaderProtocols::Udp)
+                }
+                Proto::Icmp => {
+                    ip_protocol(ether) == Some(IpNextHeaderProtocols::Icmp)
+                }
+            },
+        }
+    }
+}
+
+fn match_dir(dir: Dir, src: bool, dst: bool) -> bool {
+    return match dir {
+        Dir::Src => src,
+        Dir::Dst => dst,
+        Dir::Any => src || dst,
+    };
+}
+
+/// Source and destination IPs of IPv4 packets, or sender and target IPs of
+/// ARP packets.
+fn ips(ether: &EthernetPacket) -> Option<(Ipv4Addr, Ipv4Addr)> {
+    match ether.get_ethertype() {
+        EtherTypes::Ipv4 => {
+            let ip = Ipv4Packet::new(ether.payload())?;
+            return Some((ip.get_source(), ip.get_destination()));
+        }
+        EtherTypes::Arp => {
+            let arp = ArpPacket::new(ether.payload())?;
+            return Some((
+                arp.get_sender_proto_addr(),
+                arp.get_target_proto_addr(),
+            ));
+        }
+        _ => return None,
+    }
+}
+
+fn ip_protocol(
+    ether: &EthernetPacket,
+) -> Option<pnet::packet::ip::IpNextHeaderProtocol> {
+    if ether.get_ethertype() != EtherTypes::Ipv4 {
+        return None;
+    }
+    return Some(Ipv4Packet::new(ether.payload())?.get_next_level_protocol());
+}
+
+fn ports(ether: &EthernetPacket) -> Option<(u16, u16)> {
+    if ether.get_ethertype() != EtherTypes::Ipv4 {
+        return None;
+    }
+    let ip = Ipv4Packet::new(ether.payload())?;
+    match ip.get_next_level_protocol() {
+        IpNextHeaderProtocols::Tcp => {
+            let tcp = TcpPacket::new(ip.payload())?;
+            return Some((tcp.get_source(), tcp.get_destination()));
+        }
+        IpNextHeaderProtocols::Udp => {
+            let udp = UdpPacket::new(ip.payload())?;
+            return Some((udp.get_source(), udp.get_destination()));
+        }
+        _ => return None,
+    }
+}
+
+/// Builds the filter from the primitives of the expression.
+struct FilterBuilder;
+
+impl Builder for FilterBuilder {
+    type Filter = Filter;
+
+    fn primitive(
+        &self,
+        token: &str,
+        tokens: &mut Tokens,
+    ) -> Result<Filter, String> {
+        let proto = match token {
+            "arp" => Proto::Arp,
+            "ip" => Proto::Ip,
+            "ip6" => Proto::Ip6,
+            "tcp" => Proto::Tcp,
+            "udp" => Proto::Udp,
+            "icmp" => Proto::Icmp,
+            "ether" => {
+                let dir = parse_dir(tokens);
+                tokens.expect("host")?;
+                let mac = tokens.next()?;
+                let mac = mac
+                    .parse()
+                    .map_err(|_| format!("Invalid MAC '{}'", mac))?;
+                return Ok(Filter::EtherHost(dir, mac));
+            }
+            "src" | "dst" | "host" | "net" | "port" => {
+                let dir = match token {
+                    "src" => Dir::Src,
+                    "dst" => Dir::Dst,
+                    _ => Dir::Any,
+                };
+                let kind = match dir {
+                    Dir::Any => token.to_string(),
+                    _ => tokens.next()?,
+                };
+                return parse_qualified(dir, &kind, tokens);
+            }
+            _ => return Err(format!("Unknown primitive '{}'", token)),
+        };
+
+        // a protocol followed by a primitive qualifies it, as in
+        // `tcp port 80`, which is `tcp and port 80`
+        let qualified = match tokens.next_if(&QUALIFIERS) {
+            Some(token) => self.primitive(&token, tokens)?,
+            None => return Ok(Filter::Proto(proto)),
+        };
+        return Ok(Filter::And(
+            Box::new(Filter::Proto(proto)),
+            Box::new(qualified),
+        ));
+    }
+
+    fn and(&self, f1: Filter, f2: Filter) -> Filter {
+        return Filter::And(Box::new(f1), Box::new(f2));
+    }
+
+    fn or(&self, f1: Filter, f2: Filter) -> Filter {
+        return Filter::Or(Box::new(f1), Box::new(f2));
+    }
+
+    fn not(&self, f: Filter) -> Filter {
+        return Filter::Not(Box::new(f));
+    }
+
+    fn same_precedence(&self) -> bool {
+        return true;
+    }
+}
+
+/// Tokens that start a primitive that can qualify a protocol.
+const QUALIFIERS: [&str; 5] = ["src", "dst", "host", "net", "port"];
+
+fn parse_dir(tokens: &mut Tokens) -> Dir {
+    return match tokens.next_if(&["src", "dst"]).as_deref() {
+        Some("src") => Dir::Src,
+        Some("dst") => Dir::Dst,
+        _ => Dir::Any,
+    };
+}
+
+fn parse_qualified(
+    dir: Dir,
+    kind: &str,
+    tokens: &mut Tokens,
+) -> Result<Filter, String> {
+    let value = tokens.next()?;
+    match kind {
+        "host" => {
+            let ip = value
+                .parse()
+                .map_err(|_| format!("Invalid IP '{}'", value))?;
+            return Ok(Filter::Host(dir, ip));
+        }
+        "net" => {
+            let net = value
+                .parse()
+                .map_err(|_| format!("Invalid network '{}'", value))?;
+            return Ok(Filter::Net(dir, net));
+        }
+        "port" => {
+            let port = value
+                .parse()
+                .map_err(|_| format!("Invalid port '{}'", value))?;
+            return Ok(Filter::Port(dir, port));
+        }
+        _ => return Err(format!("Unknown primitive '{}'", kind)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pnet::packet::{
+        ethernet::MutableEthernetPacket, ipv4::MutableIpv4Packet,
+        tcp::MutableTcpPacket, MutablePacket,
+    };
+
+    /// Ethernet frame of a TCP segment from 10.0.0.1:40000 to 10.0.0.2:80.
+    fn tcp_frame() -> Vec<u8> {
+        let mut buf = vec![0u8; 14 + 20 + 20];
+        let mut ether = MutableEthernetPacket::new(&mut buf).unwrap();
+        ether.set_source(MacAddr::new(0x52, 0x54, 0, 0, 0, 1));
+        ether.set_destination(MacAddr::new(0x52, 0x54, 0, 0, 0, 2));
+        ether.set_ethertype(EtherTypes::Ipv4);
+
+        let mut ip = MutableIpv4Packet::new(ether.payload_mut()).unwrap();
+        ip.set_version(4);
+        ip.set_header_length(5);
+        ip.set_total_length(40);
+        ip.set_next_level_protocol(IpNextHeaderProtocols::Tcp);
+        ip.set_source(Ipv4Addr::new(10, 0, 0, 1));
+        ip.set_destination(Ipv4Addr::new(10, 0, 0, 2));
+
+        let mut tcp = MutableTcpPacket::new(ip.payload_mut()).unwrap();
+        tcp.set_source(40000);
+        tcp.set_destination(80);
+        return buf;
+    }
+
+    fn matches(expr: &str) -> bool {
+        return Filter::parse(expr).unwrap().matches(&tcp_frame());
+    }
+
+    #[test]
+    fn matches_primitives() {
+        assert!(matches("host 10.0.0.1"));
+        assert!(matches("src host 10.0.0.1"));
+        assert!(!matches("dst host 10.0.0.1"));
+        assert!(matches("dst net 10.0.0.0/24"));
+        assert!(!matches("net 192.168.0.0/16"));
+        assert!(matches("dst port 80"));
+        assert!(!matches("src port 80"));
+        assert!(matches("ether src host 52:54:00:00:00:01"));
+        assert!(!matches("ether dst host 52:54:00:00:00:01"));
+        assert!(matches("ip"));
+        assert!(matches("tcp"));
+        assert!(!matches("udp"));
+        assert!(!matches("arp"));
+    }
+
+    #[test]
+    fn protocol_qualifies_next_primitive() {
+        assert!(matches("tcp port 80"));
+        assert!(matches("tcp dst port 80"));
+        assert!(!matches("udp port 80"));
+        assert!(!matches("tcp src port 80"));
+        assert!(matches("ip host 10.0.0.2 and tcp"));
+    }
+
+    #[test]
+    fn combines_from_left_to_right() {
+        // and and or have the same precedence, as in tcpdump
+        assert!(matches("udp and port 53 or tcp"));
+        assert!(!matches("tcp or udp and port 53"));
+        assert!(matches("tcp or (udp and port 53)"));
+        assert!(matches("tcp or udp and port 80"));
+        assert!(!matches("tcp or port 53 and udp"));
+        assert!(!matches("udp and (port 53 or tcp)"));
+        assert!(matches("not udp and tcp"));
+        assert!(!matches("not (udp or tcp)"));
+        assert!(matches("!(udp || arp) && port 80"));
+        assert!(matches("not not tcp"));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for expr in &[
+            "",
+            "foo",
+            "host",
+            "host 10.0.0.256",
+            "port 70000",
+            "net 10.0.0.0/33",
+            "ether host 52:54",
+            "ether src 52:54:00:00:00:01",
+            "(tcp",
+            "tcp)",
+            "tcp and",
+            "tcp udp",
+        ] {
+            assert!(Filter::parse(expr).is_err(), "{}", expr);
+        }
+    }
+}
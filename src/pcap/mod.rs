@@ -0,0 +1,120 @@
+mod filter;
+
+pub use filter::Filter;
+
+use std::fs::File;
+use std::io::Write;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const MAGIC: u32 = 0xa1b2c3d4;
+const VERSION_MAJOR: u16 = 2;
+const VERSION_MINOR: u16 = 4;
+const SNAPLEN: u32 = 65535;
+const LINKTYPE_ETHERNET: u32 = 1;
+
+#[derive(Debug)]
+pub struct PcapOptions {
+    pub file: String,
+    pub rotate_size: Option<u64>,
+    pub rotate_time: Option<Duration>,
+    pub filter: Option<Filter>,
+}
+
+/// Writes frames to files in the classic pcap (libpcap) format, pcapng is not
+/// supported. When the current file reaches the rotate size
+/// or it has been open for the rotate time, the capture continues in a new
+/// file, named as the first one plus a counter (`file.1`, `file.2`...).
+pub struct PcapWriter {
+    options: PcapOptions,
+    file: File,
+    file_index: u32,
+    file_size: u64,
+    file_start: Instant,
+}
+
+impl PcapWriter {
+    pub fn create(options: PcapOptions) -> Result<Self, String> {
+        let file = create_file(&options.file)?;
+        return Ok(Self {
+            options,
+            file,
+            file_index: 0,
+            file_size: 0,
+            file_start: Instant::now(),
+        });
+    }
+
+    /// Writes the frame if it matches the filter.
+    pub fn write(&mut self, buf: &[u8]) -> Result<(), String> {
+        if let Some(filter) = &self.options.filter {
+            if !filter.matches(buf) {
+                return Ok(());
+            }
+        }
+
+        if self.must_rotate(buf.len() as u64) {
+            self.rotate()?;
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let captured = buf.len().min(SNAPLEN as usize);
+
+        let mut record = Vec::with_capacity(16 + captured);
+        record.extend(&(timestamp.as_secs() as u32).to_ne_bytes());
+        record.extend(&timestamp.subsec_micros().to_ne_bytes());
+        record.extend(&(captured as u32).to_ne_bytes());
+        record.extend(&(buf.len() as u32).to_ne_bytes());
+        record.extend(&buf[..captured]);
+
+        self.file
+            .write_all(&record)
+            .map_err(|e| format!("Error writing pcap: {}", e))?;
+        self.file_size += record.len() as u64;
+
+        return Ok(());
+    }
+
+    fn must_rotate(&self, len: u64) -> bool {
+        if let Some(rotate_size) = self.options.rotate_size {
+            if self.file_size > 0 && self.file_size + len > rotate_size {
+                return true;
+            }
+        }
+        if let Some(rotate_time) = self.options.rotate_time {
+            if self.file_start.elapsed() >= rotate_time {
+                return true;
+            }
+        }
+        return false;
+    }
+
+    fn rotate(&mut self) -> Result<(), String> {
+        self.file_index += 1;
+        let path = format!("{}.{}", self.options.file, self.file_index);
+        self.file = create_file(&path)?;
+        self.file_size = 0;
+        self.file_start = Instant::now();
+        return Ok(());
+    }
+}
+
+fn create_file(path: &str) -> Result<File, String> {
+    let mut file = File::create(path)
+        .map_err(|e| format!("Error creating pcap {}: {}", path, e))?;
+
+    let mut header = Vec::with_capacity(24);
+    header.extend(&MAGIC.to_ne_bytes());
+    header.extend(&VERSION_MAJOR.to_ne_bytes());
+    header.extend(&VERSION_MINOR.to_ne_bytes());
+    header.extend(&0i32.to_ne_bytes());
+    header.extend(&0u32.to_ne_bytes());
+    header.extend(&SNAPLEN.to_ne_bytes());
+    header.extend(&LINKTYPE_ETHERNET.to_ne_bytes());
+
+    file.write_all(&header)
+        .map_err(|e| format!("Error writing pcap {}: {}", path, e))?;
+
+    return Ok(file);
+}
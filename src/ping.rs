@@ -0,0 +1,253 @@
+use crate::args;
+use crate::arp;
+use crate::oui::OuiDb;
+use crate::validators::{self, Validator};
+use log::warn;
+use pnet::{
+    datalink::{Config, DataLinkReceiver, NetworkInterface},
+    packet::Packet,
+    util::MacAddr,
+};
+use std::io::ErrorKind;
+use std::net::Ipv4Addr;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::{Duration, Instant};
+
+/// Max time the receiver blocks before checking if it should stop.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Addresses of the ping requests.
+#[derive(Clone, Copy)]
+struct PingAddrs {
+    target_ip: Ipv4Addr,
+    source_ip: Ipv4Addr,
+    source_mac: MacAddr,
+}
+
+#[derive(Default)]
+struct PingStats {
+    sent: u64,
+    received: u64,
+    rtts: Vec<Duration>,
+    macs: Vec<MacAddr>,
+}
+
+pub fn main_ping(args: args::ping::Arguments) -> Result<(), String> {
+    let iface = &args.iface;
+    let source_mac = iface.mac.ok_or_else(|| {
+        format!("Unable to get the MAC address of {} interface", iface.name)
+    })?;
+    let source_ip = arp::get_iface_ipv4(iface).ok_or_else(|| {
+        format!("Unable to get the Ipv4 address of {} interface", iface.name)
+    })?;
+    let oui_db = OuiDb::new(args.oui_file.as_deref())?;
+
+    let running = Arc::new(AtomicBool::new(true));
+    let run_c = running.clone();
+
+    ctrlc::set_handler(move || {
+        run_c.store(false, Ordering::SeqCst);
+    })
+    .expect("Error setting Ctrl-C handler");
+
+    eprintln!("ARPING {} from {} {}", args.ip, source_ip, iface.name);
+
+    let filter = match &args.filter {
+        Some(filter) => Some(validators::parse_filter(filter)?),
+        None => None,
+    };
+
+    let addrs = PingAddrs {
+        target_ip: args.ip,
+        source_ip,
+        source_mac,
+    };
+    let stats = ping(
+        iface,
+        addrs,
+        args.interval,
+        args.count,
+        filter,
+        running,
+        &oui_db,
+    )?;
+
+    print_stats(args.ip, &stats);
+
+    return Ok(());
+}
+
+fn ping(
+    iface: &NetworkInterface,
+    addrs: PingAddrs,
+    interval: Duration,
+    count: Option<u64>,
+    filter: Option<Validator>,
+    running: Arc<AtomicBool>,
+    oui_db: &OuiDb,
+) -> Result<PingStats, String> {
+    let config = Config {
+        read_timeout: Some(POLL_INTERVAL.min(interval)),
+        ..Default::default()
+    };
+    let (mut sender, mut receiver) = arp::new_ether_channel(iface, config)?;
+    let packet = arp::ether_arp_request(
+        addrs.target_ip,
+        addrs.source_ip,
+        addrs.source_mac,
+    );
+
+    let mut stats = PingStats::default();
+
+    while running.load(Ordering::SeqCst) {
+        if let Some(count) = count {
+            if stats.sent == count {
+                break;
+            }
+        }
+
+        sender
+            .send_to(packet.packet(), None)
+            .ok_or("Error sending packet")?
+            .map_err(|e| format!("Error sending packet: {}", e))?;
+        stats.sent += 1;
+
+        wait_replies(
+            &mut receiver,
+            addrs,
+            interval,
+            filter.as_ref(),
+            &running,
+            &mut stats,
+            oui_db,
+        )?;
+    }
+
+    return Ok(stats);
+}
+
+/// Collects the replies of the last request until the interval expires. All
+/// the replies are shown, but only the first one is used for the statistics.
+/// The replies that don't match the filter, if any, are ignored.
+fn wait_replies(
+    receiver: &mut Box<dyn DataLinkReceiver>,
+    addrs: PingAddrs,
+    interval: Duration,
+    filter: Option<&Validator>,
+    running: &AtomicBool,
+    stats: &mut PingStats,
+    oui_db: &OuiDb,
+) -> Result<(), String> {
+    let target_ip = addrs.target_ip;
+    let sent_at = Instant::now();
+    let seq = stats.sent;
+    let mut replied = false;
+
+    while sent_at.elapsed() < interval && running.load(Ordering::SeqCst) {
+        let buf = match receiver.next() {
+            Ok(buf) => buf,
+            Err(e) if e.kind() == ErrorKind::TimedOut => continue,
+            Err(e) => return Err(format!("Error receiving packet: {}", e)),
+        };
+
+        let mac = match arp::parse_arp_reply(
+            buf,
+            addrs.source_ip,
+            addrs.source_mac,
+        ) {
+            Some((ip, mac)) if ip == target_ip => mac,
+            _ => continue,
+        };
+        if let Some(filter) = filter {
+            if !filter.is_valid(buf) {
+                continue;
+            }
+        }
+        let rtt = sent_at.elapsed();
+
+        if !stats.macs.contains(&mac) {
+            if !stats.macs.is_empty() {
+                warn!(
+                    "{} is also claimed by {} ({}), previously seen: {}",
+                    target_ip,
+                    mac,
+                    oui_db.describe(mac),
+                    format_macs(&stats.macs)
+                );
+            }
+            stats.macs.push(mac);
+        }
+
+        println!(
+            "Reply from {} [{}] seq={} time={:.3} ms{}",
+            target_ip,
+            mac,
+            seq,
+            rtt.as_secs_f64() * 1000.0,
+            if replied { " (DUP)" } else { "" }
+        );
+
+        if !replied {
+            replied = true;
+            stats.received += 1;
+            stats.rtts.push(rtt);
+        }
+    }
+
+    return Ok(());
+}
+
+fn print_stats(target_ip: Ipv4Addr, stats: &PingStats) {
+    println!("--- {} arping statistics ---", target_ip);
+
+    let loss = if stats.sent == 0 {
+        0.0
+    } else {
+        (stats.sent - stats.received) as f64 * 100.0 / stats.sent as f64
+    };
+    println!(
+        "{} requests sent, {} replies received, {:.1}% loss",
+        stats.sent, stats.received, loss
+    );
+
+    if !stats.rtts.is_empty() {
+        let rtts: Vec<f64> = stats
+            .rtts
+            .iter()
+            .map(|rtt| rtt.as_secs_f64() * 1000.0)
+            .collect();
+        let min = rtts.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = rtts.iter().cloned().fold(0.0, f64::max);
+        let avg = rtts.iter().sum::<f64>() / rtts.len() as f64;
+        let variance = rtts.iter().map(|rtt| (rtt - avg).powi(2)).sum::<f64>()
+            / rtts.len() as f64;
+
+        println!(
+            "rtt min/avg/max/stddev = {:.3}/{:.3}/{:.3}/{:.3} ms",
+            min,
+            avg,
+            max,
+            variance.sqrt()
+        );
+    }
+
+    if stats.macs.len() > 1 {
+        warn!(
+            "{} replied from {} different MACs: {}. Possible duplicate address or ARP spoofing",
+            target_ip,
+            stats.macs.len(),
+            format_macs(&stats.macs)
+        );
+    }
+}
+
+fn format_macs(macs: &[MacAddr]) -> String {
+    return macs
+        .iter()
+        .map(|mac| mac.to_string())
+        .collect::<Vec<String>>()
+        .join(", ");
+}
@@ -0,0 +1,31 @@
+use crate::args;
+use crate::oui::OuiDb;
+use crate::spoof::{self, session::Session};
+use log::{info, warn};
+use pnet::datalink;
+use std::fs;
+
+pub fn main_recover(args: args::recover::Arguments) -> Result<(), String> {
+    let session = Session::load(&args.session)?;
+
+    let iface = match args.iface {
+        Some(iface) => iface,
+        None => datalink::interfaces()
+            .into_iter()
+            .find(|iface| iface.name == session.iface)
+            .ok_or_else(|| {
+                format!("Interface '{}' not found in the system", session.iface)
+            })?,
+    };
+    let oui_db = OuiDb::new(args.oui_file.as_deref())?;
+
+    spoof::recover_session(&iface, &session, args.delay, args.verify, &oui_db)?;
+
+    // as spoof does, so a later recover doesn't replay a stale session
+    match fs::remove_file(&args.session) {
+        Ok(()) => info!("Removed session {}", args.session),
+        Err(e) => warn!("Error removing session {}: {}", args.session, e),
+    }
+
+    return Ok(());
+}
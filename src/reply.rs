@@ -1,22 +1,42 @@
-use crate::args::reply::{IpsVal, MacsVal};
+use crate::args::reply::{Ips, IpsVal, MacsVal};
 use crate::arp;
-use crate::validators::{DstIpValidator, SrcIpValidator, SrcMacValidator};
+use crate::macmap::MacMap;
+use crate::oui::OuiDb;
+use crate::pcap::PcapWriter;
+use crate::tarpit::Tarpit;
+use crate::validators::{
+    self, DstIpValidator, DstNetValidator, DstRangeValidator, OrValidator,
+    SrcIpValidator, SrcMacValidator, SrcNetValidator, SrcRangeValidator,
+};
 use crate::{args, validators::Validator};
 use pnet::{
-    datalink::{Config, NetworkInterface},
+    datalink::{Config, DataLinkSender, NetworkInterface},
     packet::{
         arp::{ArpOperations, ArpPacket},
-        ethernet::MutableEthernetPacket,
+        ethernet::{EtherTypes, EthernetPacket, MutableEthernetPacket},
+        ipv4::Ipv4Packet,
         Packet,
     },
     util::MacAddr,
 };
 
-use log::{debug, info};
+use log::{debug, info, warn};
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use std::net::Ipv4Addr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Max time the receiver blocks before checking if the MAC map should be
+/// reloaded or the tarpit grace periods expired.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Set by the SIGHUP handler to reload the MAC map.
+static RELOAD: AtomicBool = AtomicBool::new(false);
 
 pub fn main_reply(args: args::reply::Arguments) -> Result<(), String> {
     let iface = args.iface;
-    let my_mac = (&iface).mac.ok_or_else(|| {
+    let my_mac = iface.mac.ok_or_else(|| {
         format!("Unable to get the MAC address of {} interface", iface)
     })?;
 
@@ -25,26 +45,54 @@ pub fn main_reply(args: args::reply::Arguments) -> Result<(), String> {
         None => my_mac,
     };
 
-    let arp_validator =
+    let mut arp_validator =
         build_validator(my_mac, args.src_ips, args.src_macs, args.dst_ips);
+    if let Some(filter) = &args.filter {
+        arp_validator = arp_validator & validators::parse_filter(filter)?;
+    }
+
+    let oui_db = OuiDb::new(args.oui_file.as_deref())?;
+    let pcap = match args.pcap {
+        Some(options) => Some(PcapWriter::create(options)?),
+        None => None,
+    };
+
+    let tarpit = if args.tarpit {
+        Some(Tarpit::new(
+            args.tarpit_grace,
+            args.tarpit_requests,
+            args.tarpit_owner_timeout,
+            HashSet::from([my_mac, source_mac]),
+        ))
+    } else {
+        None
+    };
 
-    reply_to_arp(&iface, source_mac, arp_validator)?;
+    reply_to_arp(
+        &iface,
+        source_mac,
+        args.mac_map.as_deref(),
+        arp_validator,
+        tarpit,
+        pcap,
+        &oui_db,
+    )?;
 
     return Ok(());
 }
 
 fn build_validator(
     my_mac: MacAddr,
-    src_ips: Option<IpsVal>,
+    src_ips: Vec<IpsVal>,
     src_macs: Option<MacsVal>,
-    dst_ips: Option<IpsVal>,
+    dst_ips: Vec<IpsVal>,
 ) -> Validator {
     let mut arp_filter = !SrcMacValidator::new_one(my_mac);
 
-    if let Some(src_ips) = src_ips {
+    for src_ips in src_ips {
         let src_ip_val = match src_ips {
-            IpsVal::Filter(ips) => !SrcIpValidator::new(ips),
-            IpsVal::Match(ips) => SrcIpValidator::new(ips),
+            IpsVal::Filter(ips) => !src_ips_validator(ips),
+            IpsVal::Match(ips) => src_ips_validator(ips),
         };
         arp_filter = arp_filter & src_ip_val;
     }
@@ -57,10 +105,10 @@ fn build_validator(
         arp_filter = arp_filter & src_mac_val;
     }
 
-    if let Some(dst_ips) = dst_ips {
+    for dst_ips in dst_ips {
         let dst_ip_val = match dst_ips {
-            IpsVal::Filter(ips) => !DstIpValidator::new(ips),
-            IpsVal::Match(ips) => DstIpValidator::new(ips),
+            IpsVal::Filter(ips) => !dst_ips_validator(ips),
+            IpsVal::Match(ips) => dst_ips_validator(ips),
         };
         arp_filter = arp_filter & dst_ip_val;
     }
@@ -68,61 +116,319 @@ fn build_validator(
     return arp_filter;
 }
 
+/// Validator that accepts the sender IPs that are any of the single IPs, or
+/// inside any of the networks or ranges.
+fn src_ips_validator(ips: Ips) -> Validator {
+    return OrValidator::new(vec![
+        SrcIpValidator::new(ips.ips),
+        SrcNetValidator::new(ips.nets),
+        SrcRangeValidator::new(ips.ranges),
+    ]);
+}
+
+/// Validator that accepts the target IPs that are any of the single IPs, or
+/// inside any of the networks or ranges.
+fn dst_ips_validator(ips: Ips) -> Validator {
+    return OrValidator::new(vec![
+        DstIpValidator::new(ips.ips),
+        DstNetValidator::new(ips.nets),
+        DstRangeValidator::new(ips.ranges),
+    ]);
+}
+
+/// Replies the requests with `source_mac`, or with the MAC of the requested IP
+/// in the MAC map file, if any, which is reloaded on SIGHUP. With a tarpit,
+/// only the IPs that nobody else claims are answered.
 fn reply_to_arp(
     iface: &NetworkInterface,
     source_mac: MacAddr,
+    mac_map_file: Option<&str>,
     arp_validator: Validator,
+    mut tarpit: Option<Tarpit>,
+    mut pcap: Option<PcapWriter>,
+    oui_db: &OuiDb,
 ) -> Result<(), String> {
+    let mut mac_map = match mac_map_file {
+        Some(file) => {
+            let mac_map = MacMap::load(file)?;
+            info!("Loaded {} entries from {}", mac_map.entries_count(), file);
+            set_reload_handler()?;
+            mac_map
+        }
+        None => MacMap::single(source_mac),
+    };
+    // IPs whose traffic is intercepted, to only capture that traffic
+    let mut answered = HashSet::new();
+
+    let config = Config {
+        read_timeout: Some(POLL_INTERVAL),
+        ..Default::default()
+    };
     let (mut sender, mut receiver) =
-        arp::new_ether_channel(iface, Config::default()).unwrap();
+        arp::new_ether_channel(iface, config).unwrap();
 
     loop {
-        let buf = receiver
-            .next()
-            .map_err(|e| format!("Error receiving packet: {}", e))?;
-        if !arp::is_arp(buf) {
-            continue;
+        if let Some(file) = mac_map_file {
+            if RELOAD.swap(false, Ordering::SeqCst) {
+                reload_mac_map(&mut mac_map, file);
+            }
         }
 
-        let arp_msg = ArpPacket::new(
-            &buf[MutableEthernetPacket::minimum_packet_size()..],
-        )
-        .unwrap();
+        if let Some(tarpit) = &mut tarpit {
+            for request in tarpit.expired() {
+                if send_reply(&mut sender, &request, &mac_map, oui_db)? {
+                    answered.insert(request.target_ip);
+                }
+            }
+        }
+
+        let buf = match receiver.next() {
+            Ok(buf) => buf,
+            Err(e)
+                if e.kind() == ErrorKind::TimedOut
+                    || e.kind() == ErrorKind::Interrupted =>
+            {
+                continue
+            }
+            Err(e) => return Err(format!("Error receiving packet: {}", e)),
+        };
+        match &mut tarpit {
+            Some(tarpit) => {
+                if let Some((ip, mac)) = arp::parse_arp_sender(buf) {
+                    // the MACs of the map are ours too, but they can change
+                    // on reload
+                    if !mac_map.contains_mac(mac) {
+                        tarpit.see_owner(ip, mac);
+                    }
+                }
+                if let Some(request) =
+                    accept_request(buf, &arp_validator, oui_db)
+                {
+                    for request in tarpit.on_request(request) {
+                        if send_reply(&mut sender, &request, &mac_map, oui_db)?
+                        {
+                            answered.insert(request.target_ip);
+                        }
+                    }
+                }
+            }
+            None => {
+                if let Some(request) =
+                    accept_request(buf, &arp_validator, oui_db)
+                {
+                    if send_reply(&mut sender, &request, &mac_map, oui_db)? {
+                        answered.insert(request.target_ip);
+                    }
+                }
+            }
+        }
 
-        if arp_msg.get_operation() != ArpOperations::Request {
-            continue;
+        if let Some(pcap) = &mut pcap {
+            if is_intercepted(buf, &mac_map, &answered) {
+                pcap.write(buf)?;
+            }
         }
+    }
+}
+
+/// Whether the frame was sent to a replied MAC for an answered IP, so it is
+/// traffic intercepted with the replies.
+fn is_intercepted(
+    buf: &[u8],
+    mac_map: &MacMap,
+    answered: &HashSet<Ipv4Addr>,
+) -> bool {
+    let ether = match EthernetPacket::new(buf) {
+        Some(ether) => ether,
+        None => return false,
+    };
+    if !mac_map.contains_mac(ether.get_destination()) {
+        return false;
+    }
 
-        let sender_mac = arp_msg.get_sender_hw_addr();
-        let sender_ip = arp_msg.get_sender_proto_addr();
-        let target_ip = arp_msg.get_target_proto_addr();
+    let destination = match ether.get_ethertype() {
+        EtherTypes::Ipv4 => match Ipv4Packet::new(ether.payload()) {
+            Some(ip) => ip.get_destination(),
+            None => return false,
+        },
+        EtherTypes::Arp => match ArpPacket::new(ether.payload()) {
+            Some(arp_msg) => arp_msg.get_target_proto_addr(),
+            None => return false,
+        },
+        _ => return false,
+    };
+    return answered.contains(&destination);
+}
 
-        if !arp_validator.is_valid_arp(&arp_msg) {
-            debug!(
-                "Ignore request for {} from {} ({})",
-                target_ip, sender_ip, sender_mac
-            );
-            continue;
+/// Replaces the MAC map with the one of the file, or keeps the current one if
+/// the file can't be loaded, so a wrong edit doesn't stop the replies.
+fn reload_mac_map(mac_map: &mut MacMap, file: &str) {
+    match MacMap::load(file) {
+        Ok(new_map) => {
+            *mac_map = new_map;
+            info!("Reloaded {} entries from {}", mac_map.entries_count(), file);
         }
+        Err(e) => warn!("{}", e),
+    }
+}
 
-        info!(
-            "Reply request for {} from {} ({})",
-            target_ip, sender_ip, sender_mac
-        );
+extern "C" fn on_sighup(_: libc::c_int) {
+    RELOAD.store(true, Ordering::SeqCst);
+}
+
+/// Sets the SIGHUP handler that flags the reload of the MAC map. The
+/// interrupted reads are restarted, so the receiver only notices the flag on
+/// its next timeout.
+fn set_reload_handler() -> Result<(), String> {
+    let handler = on_sighup as extern "C" fn(libc::c_int);
+    // SAFETY: the action is fully initialized before it is installed, and the
+    // handler only stores to an atomic, which is async-signal-safe.
+    let res = unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handler as libc::sighandler_t;
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(libc::SIGHUP, &action, std::ptr::null_mut())
+    };
+    if res != 0 {
+        return Err(format!(
+            "Error setting SIGHUP handler: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    return Ok(());
+}
+
+/// ARP request received from a host.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub sender_ip: Ipv4Addr,
+    pub sender_mac: MacAddr,
+    pub target_ip: Ipv4Addr,
+}
+
+/// Replies the frame if it is an ARP request accepted by the validator,
+/// telling the requester that the requested IP is at the MAC of the map.
+pub fn reply_request(
+    sender: &mut Box<dyn DataLinkSender>,
+    buf: &[u8],
+    arp_validator: &Validator,
+    mac_map: &MacMap,
+    oui_db: &OuiDb,
+) -> Result<(), String> {
+    if let Some(request) = accept_request(buf, arp_validator, oui_db) {
+        send_reply(sender, &request, mac_map, oui_db)?;
+    }
+    return Ok(());
+}
+
+/// Returns the request of the frame, if it is an ARP request accepted by the
+/// validator.
+fn accept_request(
+    buf: &[u8],
+    arp_validator: &Validator,
+    oui_db: &OuiDb,
+) -> Option<Request> {
+    if !arp::is_arp(buf) {
+        return None;
+    }
+
+    let ether = EthernetPacket::new(buf).unwrap();
+    let arp_msg =
+        ArpPacket::new(&buf[MutableEthernetPacket::minimum_packet_size()..])
+            .unwrap();
+
+    if arp_msg.get_operation() != ArpOperations::Request {
+        return None;
+    }
 
-        let target_mac = sender_mac;
-        let (source_ip, target_ip) = (target_ip, sender_ip);
+    let request = Request {
+        sender_ip: arp_msg.get_sender_proto_addr(),
+        sender_mac: arp_msg.get_sender_hw_addr(),
+        target_ip: arp_msg.get_target_proto_addr(),
+    };
 
-        let ether_packet = arp::new_ether_arp_reply(
-            target_ip, target_mac, source_ip, source_mac,
+    if !arp_validator.is_valid_arp(&ether, &arp_msg) {
+        debug!(
+            "Ignore request for {} from {} ({}, {})",
+            request.target_ip,
+            request.sender_ip,
+            request.sender_mac,
+            oui_db.describe(request.sender_mac)
         );
+        return None;
+    }
+
+    return Some(request);
+}
+
+/// Tells the requester that the requested IP is at the MAC of the map.
+/// Returns whether the reply was sent, since the IP may have no MAC mapped.
+fn send_reply(
+    sender: &mut Box<dyn DataLinkSender>,
+    request: &Request,
+    mac_map: &MacMap,
+    oui_db: &OuiDb,
+) -> Result<bool, String> {
+    let source_mac = match mac_map.lookup(request.target_ip) {
+        Some(mac) => mac,
+        None => {
+            debug!("Ignore request for {}: no MAC mapped", request.target_ip);
+            return Ok(false);
+        }
+    };
+
+    info!(
+        "Reply request for {} from {} ({}, {})",
+        request.target_ip,
+        request.sender_ip,
+        request.sender_mac,
+        oui_db.describe(request.sender_mac)
+    );
+
+    let ether_packet = arp::new_ether_arp_reply(
+        request.sender_ip,
+        request.sender_mac,
+        request.target_ip,
+        source_mac,
+    );
+
+    sender
+        .send_to(ether_packet.packet(), None)
+        .ok_or("Error sending packet")?
+        .map_err(|e| format!("Error sending packet: {}", e))?;
+
+    return Ok(true);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    #[test]
+    fn reload_replaces_map_or_keeps_it_on_error() {
+        let path = env::temp_dir()
+            .join(format!("arplayer-test-{}.macmap", process::id()));
+        let path = path.to_str().unwrap();
+        let ip = Ipv4Addr::new(10, 0, 0, 1);
+        let old_mac = MacAddr::new(0x52, 0x54, 0, 0, 0, 1);
+        let new_mac = MacAddr::new(0x52, 0x54, 0, 0, 0, 2);
+        let mut mac_map = MacMap::single(old_mac);
+
+        fs::write(path, "10.0.0.1 52:54:00:00:00:02\n").unwrap();
+        reload_mac_map(&mut mac_map, path);
+        assert_eq!(mac_map.lookup(ip), Some(new_mac));
+        assert_eq!(mac_map.lookup(Ipv4Addr::new(10, 0, 0, 2)), None);
 
-        sender
-            .send_to(ether_packet.packet(), None)
-            .ok_or("Error sending packet")
-            .unwrap()
-            .map_err(|e| format!("Error sending packet: {}", e))?;
+        // the map is not partially updated with the lines before the error
+        fs::write(path, "* 52:54:00:00:00:01\n10.0.0.1 zz\n").unwrap();
+        reload_mac_map(&mut mac_map, path);
+        assert_eq!(mac_map.lookup(ip), Some(new_mac));
+        assert_eq!(mac_map.lookup(Ipv4Addr::new(10, 0, 0, 2)), None);
 
-        // println!("{:?}", arp);
+        fs::remove_file(path).unwrap();
+        reload_mac_map(&mut mac_map, path);
+        assert_eq!(mac_map.lookup(ip), Some(new_mac));
     }
 }
@@ -0,0 +1,97 @@
+use std::fs;
+use std::net::Ipv4Addr;
+
+const ROUTE_FILE: &str = "/proc/net/route";
+
+const RTF_UP: u32 = 0x0001;
+const RTF_GATEWAY: u32 = 0x0002;
+
+/// Returns the gateway of the default route of the interface, from the kernel
+/// routing table. If there are several default routes, the one with the lowest
+/// metric is returned.
+pub fn get_default_gateway(
+    iface_name: &str,
+) -> Result<Option<Ipv4Addr>, String> {
+    let content = fs::read_to_string(ROUTE_FILE)
+        .map_err(|e| format!("Error reading {}: {}", ROUTE_FILE, e))?;
+    return Ok(parse_default_gateway(&content, iface_name));
+}
+
+/// Parses the routing table, in the format of the route file.
+fn parse_default_gateway(content: &str, iface_name: &str) -> Option<Ipv4Addr> {
+    let mut gateway = None;
+    let mut best_metric = u32::MAX;
+
+    // Iface Destination Gateway Flags RefCnt Use Metric Mask MTU Window IRTT
+    for line in content.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 8 || fields[0] != iface_name {
+            continue;
+        }
+
+        let destination = parse_hex_u32(fields[1]);
+        let flags = parse_hex_u32(fields[3]);
+        let mask = parse_hex_u32(fields[7]);
+        let metric = fields[6].parse::<u32>().ok();
+
+        let (destination, flags, mask, metric) =
+            match (destination, flags, mask, metric) {
+                (Some(d), Some(f), Some(m), Some(me)) => (d, f, m, me),
+                _ => continue,
+            };
+
+        if destination != 0 || mask != 0 {
+            continue;
+        }
+        if flags & RTF_UP == 0 || flags & RTF_GATEWAY == 0 {
+            continue;
+        }
+
+        if let Some(ip) = parse_hex_ip(fields[2]) {
+            if metric < best_metric {
+                best_metric = metric;
+                gateway = Some(ip);
+            }
+        }
+    }
+
+    return gateway;
+}
+
+fn parse_hex_u32(hex: &str) -> Option<u32> {
+    return u32::from_str_radix(hex, 16).ok();
+}
+
+/// The addresses are written as the hexadecimal of the network order bytes
+/// read as a native integer.
+fn parse_hex_ip(hex: &str) -> Option<Ipv4Addr> {
+    return parse_hex_u32(hex).map(|ip| Ipv4Addr::from(ip.to_ne_bytes()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROUTES: &str = "\
+Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+eth0\t00000000\t0164A8C0\t0003\t0\t0\t600\t00000000\t0\t0\t0
+eth0\t0064A8C0\t00000000\t0001\t0\t0\t600\t00FFFFFF\t0\t0\t0
+eth0\t00000000\t0264A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0
+eth0\t00000000\t0364A8C0\t0001\t0\t0\t0\t00000000\t0\t0\t0
+wlan0\t00000000\t0101A8C0\t0003\t0\t0\t0\t00000000\t0\t0\t0
+";
+
+    #[test]
+    fn picks_default_route_with_lowest_metric() {
+        // the route without the gateway flag is skipped despite its metric
+        assert_eq!(
+            parse_default_gateway(ROUTES, "eth0"),
+            Some(Ipv4Addr::new(192, 168, 100, 2))
+        );
+        assert_eq!(
+            parse_default_gateway(ROUTES, "wlan0"),
+            Some(Ipv4Addr::new(192, 168, 1, 1))
+        );
+        assert_eq!(parse_default_gateway(ROUTES, "eth1"), None);
+    }
+}
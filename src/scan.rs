@@ -1,13 +1,19 @@
 use crate::args::{self, scan::Target};
 use crate::arp;
-use log::{debug, info};
+use crate::oui::OuiDb;
+use crate::output::{HostRecord, ScanPrinter, SummaryRecord};
+use crate::route;
+use crate::scanner::{self, ScanOptions};
+use crate::validators;
+use log::{debug, info, warn};
 use pnet::ipnetwork::Ipv4Network;
-use threadpool::ThreadPool;
+use std::time::SystemTime;
 
 pub fn main_scan(args: args::scan::Arguments) -> Result<(), String> {
-    let pool = ThreadPool::new(args.workers as usize);
+    if args.workers.is_some() {
+        warn!("--workers is deprecated and ignored, use --rate instead");
+    }
 
-    let timeout = args.timeout;
     let iface_ipv4_net =
         arp::get_iface_ipv4_network(&args.iface).ok_or_else(|| {
             format!(
@@ -16,14 +22,11 @@ pub fn main_scan(args: args::scan::Arguments) -> Result<(), String> {
             )
         })?;
     let source_ip = iface_ipv4_net.ip();
-    let source_mac = (&args.iface)
-        .mac
-        .ok_or_else(|| {
-            format!("Unable to get the MAC address of {} interface", args.iface)
-        })?
-        .clone();
-
-    let ips = match args.target {
+    let source_mac = args.iface.mac.ok_or_else(|| {
+        format!("Unable to get the MAC address of {} interface", args.iface)
+    })?;
+
+    let net = match args.target {
         Some(t) => match t {
             Target::Ip(ip) => {
                 info!("Scanning {}", ip);
@@ -33,28 +36,88 @@ pub fn main_scan(args: args::scan::Arguments) -> Result<(), String> {
                 info!("Scanning {}", net);
                 net
             }
-        }
-        .iter(),
+        },
         None => {
             info!("Scanning {}", iface_ipv4_net);
-            iface_ipv4_net.iter()
+            *iface_ipv4_net
+        }
+    };
+
+    let options = ScanOptions {
+        rate: args.rate,
+        timeout: args.timeout,
+        retries: args.retries,
+        backoff: args.backoff,
+        filter: match &args.filter {
+            Some(filter) => Some(validators::parse_filter(filter)?),
+            None => None,
+        },
+    };
+
+    let oui_db = OuiDb::new(args.oui_file.as_deref())?;
+    let vendor_filter = args.vendor.as_ref().map(|v| v.to_lowercase());
+
+    let gateway = match route::get_default_gateway(&args.iface.name) {
+        Ok(gateway) => gateway,
+        Err(e) => {
+            debug!("Unable to get the default gateway: {}", e);
+            None
         }
     };
 
-    for target_ip in ips {
-        let iface = args.iface.clone();
+    let iface_name = &args.iface.name;
+    let mut printer = ScanPrinter::new(args.output_format);
 
-        pool.execute(move || {
-            match arp::request_arp(
-                &iface, target_ip, source_ip, source_mac, timeout,
-            ) {
-                Ok(target_mac) => println!("{} {}", target_ip, target_mac),
-                Err(e) => debug!("{}: {}", target_ip, e),
+    let stats = scanner::scan(
+        &args.iface,
+        Box::new(scanner::host_ips(net)),
+        source_ip,
+        source_mac,
+        &options,
+        |reply| {
+            debug!(
+                "{} replied in {:?} after {} requests",
+                reply.ip, reply.rtt, reply.tries
+            );
+            let mac_info = oui_db.describe(reply.mac);
+            if let Some(vendor_filter) = &vendor_filter {
+                let matches = mac_info
+                    .vendor
+                    .as_ref()
+                    .map(|v| v.to_lowercase().contains(vendor_filter))
+                    .unwrap_or(false);
+                if !matches {
+                    debug!("{}: Skipped by vendor filter", reply.ip);
+                    return;
+                }
             }
-        });
-    }
 
-    pool.join();
+            printer.print_host(HostRecord {
+                ip: reply.ip,
+                mac: reply.mac,
+                mac_info,
+                gateway: gateway == Some(reply.ip),
+                iface: iface_name.clone(),
+                rtt: reply.rtt,
+                tries: reply.tries,
+                timestamp: SystemTime::now(),
+            });
+        },
+    )?;
+
+    info!(
+        "{} hosts up, {} needed a retry ({} requests sent, {} timed out)",
+        stats.replied, stats.retried, stats.sent, stats.timed_out
+    );
+
+    printer.finish(SummaryRecord {
+        iface: iface_name.clone(),
+        sent: stats.sent,
+        hosts: stats.replied,
+        retried: stats.retried,
+        timed_out: stats.timed_out,
+        timestamp: SystemTime::now(),
+    });
 
     return Ok(());
 }
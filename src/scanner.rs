@@ -0,0 +1,375 @@
+use crate::arp;
+use crate::validators::Validator;
+use log::debug;
+use pnet::{
+    datalink::{Config, DataLinkSender, NetworkInterface},
+    ipnetwork::Ipv4Network,
+    packet::Packet,
+    util::MacAddr,
+};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::io::ErrorKind;
+use std::net::Ipv4Addr;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+
+/// Max time the receiver blocks before checking for expired requests.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+pub type Targets = Box<dyn Iterator<Item = Ipv4Addr> + Send>;
+
+type InFlight = Arc<Mutex<HashMap<Ipv4Addr, Request>>>;
+
+/// Hosts waiting to be requested again, ordered by the time they are due.
+type Retries = Arc<Mutex<BinaryHeap<Reverse<(Instant, Ipv4Addr)>>>>;
+
+pub struct ScanOptions {
+    /// Max number of requests per second (retries included). If none, there
+    /// is no limit.
+    pub rate: Option<u64>,
+    /// Time to wait for the reply of each request.
+    pub timeout: Duration,
+    /// Number of times a host is requested again when it doesn't reply.
+    pub retries: u32,
+    /// Time to wait before the first retry of a host. It is doubled on each
+    /// subsequent retry.
+    pub backoff: Duration,
+    /// Filter of the replies. The hosts whose reply doesn't match it are not
+    /// reported, although they are not requested again.
+    pub filter: Option<Validator>,
+}
+
+pub struct HostReply {
+    pub ip: Ipv4Addr,
+    pub mac: MacAddr,
+    pub rtt: Duration,
+    /// Number of requests sent to the host before it replied.
+    pub tries: u32,
+}
+
+#[derive(Default)]
+pub struct ScanStats {
+    pub sent: u64,
+    pub replied: u64,
+    /// Hosts that replied after being requested more than once.
+    pub retried: u64,
+    pub timed_out: u64,
+}
+
+/// State shared by the thread that sends the requests and the one that
+/// receives the replies.
+#[derive(Clone)]
+struct Shared {
+    in_flight: InFlight,
+    retries: Retries,
+    /// Cleared when there are no more new targets to request.
+    sending: Arc<AtomicBool>,
+    /// Cleared when the scan ends.
+    running: Arc<AtomicBool>,
+}
+
+/// Spaces the packets to not exceed a rate.
+pub struct RateLimiter {
+    interval: Option<Duration>,
+    next_send: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a limiter of the given packets per second. If none, there is
+    /// no limit.
+    pub fn new(rate: Option<u64>) -> Self {
+        return Self {
+            interval: rate.map(|r| Duration::from_nanos(1_000_000_000 / r)),
+            next_send: Instant::now(),
+        };
+    }
+
+    /// Blocks until the next packet can be sent.
+    pub fn wait(&mut self) {
+        if let Some(interval) = self.interval {
+            let now = Instant::now();
+            if self.next_send > now {
+                sleep(self.next_send - now);
+            } else {
+                self.next_send = now;
+            }
+            self.next_send += interval;
+        }
+    }
+}
+
+struct Request {
+    sent_at: Instant,
+    expires_at: Instant,
+    tries: u32,
+}
+
+/// Sends an ARP request to every target through a single channel and calls
+/// `on_reply` for each host that answers. A background thread streams the
+/// requests while the current one matches the replies against the table of
+/// in-flight requests.
+pub fn scan<F>(
+    iface: &NetworkInterface,
+    targets: Targets,
+    source_ip: Ipv4Addr,
+    source_mac: MacAddr,
+    options: &ScanOptions,
+    mut on_reply: F,
+) -> Result<ScanStats, String>
+where
+    F: FnMut(HostReply),
+{
+    let config = Config {
+        read_timeout: Some(POLL_INTERVAL),
+        ..Default::default()
+    };
+    let (sender, mut receiver) = arp::new_ether_channel(iface, config)?;
+
+    let shared = Shared {
+        in_flight: Arc::new(Mutex::new(HashMap::new())),
+        retries: Arc::new(Mutex::new(BinaryHeap::new())),
+        sending: Arc::new(AtomicBool::new(true)),
+        running: Arc::new(AtomicBool::new(true)),
+    };
+    let Shared {
+        in_flight,
+        retries,
+        sending,
+        running,
+    } = shared.clone();
+
+    let send_thread = {
+        let rate = options.rate;
+        let timeout = options.timeout;
+        thread::spawn(move || {
+            let sending = shared.sending.clone();
+            let res = send_requests(
+                sender, targets, source_ip, source_mac, rate, timeout, shared,
+            );
+            sending.store(false, Ordering::SeqCst);
+            res
+        })
+    };
+
+    let mut stats = ScanStats::default();
+    let mut last_sweep = Instant::now();
+    loop {
+        match receiver.next() {
+            Ok(buf) => {
+                if let Some((ip, mac)) =
+                    arp::parse_arp_reply(buf, source_ip, source_mac)
+                {
+                    let request = in_flight.lock().unwrap().remove(&ip);
+                    if let Some(request) = request {
+                        stats.replied += 1;
+                        if request.tries > 1 {
+                            stats.retried += 1;
+                        }
+                        let matches = match &options.filter {
+                            Some(filter) => filter.is_valid(buf),
+                            None => true,
+                        };
+                        if matches {
+                            on_reply(HostReply {
+                                ip,
+                                mac,
+                                rtt: request.sent_at.elapsed(),
+                                tries: request.tries,
+                            });
+                        } else {
+                            debug!("{}: Skipped by filter", ip);
+                        }
+                    }
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::TimedOut => {}
+            Err(e) => {
+                running.store(false, Ordering::SeqCst);
+                return Err(format!("Error receiving packet: {}", e));
+            }
+        }
+
+        if send_thread.is_finished() {
+            // the sender only stops by itself due to an error
+            break;
+        }
+
+        if last_sweep.elapsed() < POLL_INTERVAL {
+            continue;
+        }
+        last_sweep = Instant::now();
+
+        let sending = sending.load(Ordering::SeqCst);
+        let mut in_flight = in_flight.lock().unwrap();
+        let now = Instant::now();
+        in_flight.retain(|ip, request| {
+            if request.expires_at > now {
+                return true;
+            }
+
+            if request.tries > options.retries {
+                debug!("{}: Timed out", ip);
+                stats.timed_out += 1;
+                return false;
+            }
+
+            let backoff =
+                options.backoff * 2u32.pow((request.tries - 1).min(16));
+            debug!("{}: Timed out, retrying in {:?}", ip, backoff);
+            // keep waiting for a late reply until the retry is sent
+            request.expires_at = now + backoff + options.timeout;
+            retries.lock().unwrap().push(Reverse((now + backoff, *ip)));
+            return true;
+        });
+
+        if !sending && in_flight.is_empty() {
+            break;
+        }
+    }
+
+    running.store(false, Ordering::SeqCst);
+
+    stats.sent = send_thread
+        .join()
+        .map_err(|_| "Error sending packets: Sender thread panicked")??;
+
+    return Ok(stats);
+}
+
+fn send_requests(
+    mut sender: Box<dyn DataLinkSender>,
+    mut targets: Targets,
+    source_ip: Ipv4Addr,
+    source_mac: MacAddr,
+    rate: Option<u64>,
+    timeout: Duration,
+    shared: Shared,
+) -> Result<u64, String> {
+    let Shared {
+        in_flight,
+        retries,
+        sending,
+        running,
+    } = shared;
+    let mut limiter = RateLimiter::new(rate);
+    let mut sent = 0;
+
+    while running.load(Ordering::SeqCst) {
+        let (target_ip, is_retry) =
+            match next_target(&mut targets, &retries, &sending) {
+                Some(target) => target,
+                None => {
+                    sleep(POLL_INTERVAL / 5);
+                    continue;
+                }
+            };
+
+        limiter.wait();
+
+        let packet = arp::ether_arp_request(target_ip, source_ip, source_mac);
+        {
+            let now = Instant::now();
+            let mut in_flight = in_flight.lock().unwrap();
+            if is_retry {
+                match in_flight.get_mut(&target_ip) {
+                    Some(request) => {
+                        request.sent_at = now;
+                        request.expires_at = now + timeout;
+                        request.tries += 1;
+                    }
+                    // it replied while waiting for the retry
+                    None => continue,
+                }
+            } else {
+                in_flight.insert(
+                    target_ip,
+                    Request {
+                        sent_at: now,
+                        expires_at: now + timeout,
+                        tries: 1,
+                    },
+                );
+            }
+        }
+        sender
+            .send_to(packet.packet(), None)
+            .ok_or("Error sending packet")?
+            .map_err(|e| format!("Error sending packet: {}", e))?;
+        sent += 1;
+    }
+
+    return Ok(sent);
+}
+
+/// Returns the IPs of the hosts of the network, skipping the network and
+/// broadcast addresses, which /31 and /32 networks don't have.
+pub fn host_ips(net: Ipv4Network) -> impl Iterator<Item = Ipv4Addr> + Send {
+    let network = net.network();
+    let broadcast = net.broadcast();
+    let has_hosts_only = net.prefix() >= 31;
+    return net.iter().filter(move |ip| {
+        has_hosts_only || (*ip != network && *ip != broadcast)
+    });
+}
+
+/// Returns the next host to request and whether it is a retry, giving priority
+/// to the due retries. When there are no more new targets, `sending` is
+/// cleared.
+fn next_target(
+    targets: &mut Targets,
+    retries: &Retries,
+    sending: &AtomicBool,
+) -> Option<(Ipv4Addr, bool)> {
+    {
+        let mut retries = retries.lock().unwrap();
+        if let Some(Reverse((due, ip))) = retries.peek() {
+            if *due <= Instant::now() {
+                let ip = *ip;
+                retries.pop();
+                return Some((ip, true));
+            }
+        }
+    }
+
+    if !sending.load(Ordering::SeqCst) {
+        return None;
+    }
+
+    match targets.next() {
+        Some(ip) => return Some((ip, false)),
+        None => {
+            sending.store(false, Ordering::SeqCst);
+            return None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hosts(net: &str) -> Vec<Ipv4Addr> {
+        return host_ips(net.parse().unwrap()).collect();
+    }
+
+    #[test]
+    fn skips_network_and_broadcast() {
+        let ips = hosts("192.168.1.0/30");
+        assert_eq!(
+            ips,
+            vec![Ipv4Addr::new(192, 168, 1, 1), Ipv4Addr::new(192, 168, 1, 2)]
+        );
+        assert_eq!(hosts("192.168.1.0/24").len(), 254);
+    }
+
+    #[test]
+    fn keeps_all_addresses_of_point_to_point_nets() {
+        assert_eq!(hosts("10.0.0.0/31").len(), 2);
+        assert_eq!(hosts("10.0.0.7/32"), vec![Ipv4Addr::new(10, 0, 0, 7)]);
+    }
+}
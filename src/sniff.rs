@@ -0,0 +1,198 @@
+use crate::args;
+use crate::arp;
+use crate::oui::OuiDb;
+use crate::output::format_timestamp;
+use crate::validators::{self, Validator};
+use log::{debug, info};
+use pnet::{
+    datalink::{Config, NetworkInterface},
+    packet::{
+        arp::{ArpOperations, ArpPacket},
+        ethernet::{EthernetPacket, MutableEthernetPacket},
+    },
+    util::MacAddr,
+};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::Ipv4Addr;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Max time the receiver blocks before checking if it should stop.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+struct HostEntry {
+    first_seen: SystemTime,
+    last_seen: SystemTime,
+    requests: u64,
+    replies: u64,
+    gratuitous: u64,
+}
+
+impl HostEntry {
+    fn packets(&self) -> u64 {
+        return self.requests + self.replies + self.gratuitous;
+    }
+}
+
+type HostTable = HashMap<(Ipv4Addr, MacAddr), HostEntry>;
+
+pub fn main_sniff(args: args::sniff::Arguments) -> Result<(), String> {
+    let oui_db = OuiDb::new(args.oui_file.as_deref())?;
+
+    let running = Arc::new(AtomicBool::new(true));
+    let run_c = running.clone();
+
+    ctrlc::set_handler(move || {
+        run_c.store(false, Ordering::SeqCst);
+    })
+    .expect("Error setting Ctrl-C handler");
+
+    eprintln!(
+        "Sniffing ARP on {} ({})",
+        args.iface.name,
+        match args.duration {
+            Some(d) => format!("{} seconds", d.as_secs()),
+            None => "until Ctrl-C".to_string(),
+        }
+    );
+
+    let filter = match &args.filter {
+        Some(filter) => Some(validators::parse_filter(filter)?),
+        None => None,
+    };
+
+    let hosts =
+        sniff_arp(&args.iface, args.duration, filter, running, &oui_db)?;
+
+    print_hosts(&hosts, &oui_db);
+
+    return Ok(());
+}
+
+/// Listens ARP packets (the ones that match the filter, if any) and builds a
+/// table with the hosts that send them. The channel sender is dropped, so
+/// nothing is transmitted.
+fn sniff_arp(
+    iface: &NetworkInterface,
+    duration: Option<Duration>,
+    filter: Option<Validator>,
+    running: Arc<AtomicBool>,
+    oui_db: &OuiDb,
+) -> Result<HostTable, String> {
+    let config = Config {
+        read_timeout: Some(POLL_INTERVAL),
+        ..Default::default()
+    };
+    let (sender, mut receiver) = arp::new_ether_channel(iface, config)?;
+    drop(sender);
+
+    let mut hosts = HostTable::new();
+    let start = Instant::now();
+
+    while running.load(Ordering::SeqCst) {
+        if let Some(duration) = duration {
+            if start.elapsed() >= duration {
+                break;
+            }
+        }
+
+        let buf = match receiver.next() {
+            Ok(buf) => buf,
+            Err(e) if e.kind() == ErrorKind::TimedOut => continue,
+            Err(e) => return Err(format!("Error receiving packet: {}", e)),
+        };
+        if !arp::is_arp(buf) {
+            continue;
+        }
+
+        let arp_msg = match ArpPacket::new(
+            &buf[MutableEthernetPacket::minimum_packet_size()..],
+        ) {
+            Some(arp_msg) => arp_msg,
+            None => continue,
+        };
+
+        if let Some(filter) = &filter {
+            let ether = EthernetPacket::new(buf).unwrap();
+            if !filter.is_valid_arp(&ether, &arp_msg) {
+                continue;
+            }
+        }
+
+        let sender_mac = arp_msg.get_sender_hw_addr();
+        let sender_ip = arp_msg.get_sender_proto_addr();
+        let target_ip = arp_msg.get_target_proto_addr();
+
+        if sender_ip.is_unspecified() {
+            debug!("Ignore probe for {} from {}", target_ip, sender_mac);
+            continue;
+        }
+
+        let now = SystemTime::now();
+        let entry = hosts.entry((sender_ip, sender_mac)).or_insert_with(|| {
+            info!(
+                "New host {} ({}, {})",
+                sender_ip,
+                sender_mac,
+                oui_db.describe(sender_mac)
+            );
+            HostEntry {
+                first_seen: now,
+                last_seen: now,
+                requests: 0,
+                replies: 0,
+                gratuitous: 0,
+            }
+        });
+        entry.last_seen = now;
+
+        if sender_ip == target_ip {
+            entry.gratuitous += 1;
+        } else if arp_msg.get_operation() == ArpOperations::Request {
+            entry.requests += 1;
+        } else if arp_msg.get_operation() == ArpOperations::Reply {
+            entry.replies += 1;
+        }
+    }
+
+    return Ok(hosts);
+}
+
+fn print_hosts(hosts: &HostTable, oui_db: &OuiDb) {
+    let mut keys: Vec<&(Ipv4Addr, MacAddr)> = hosts.keys().collect();
+    keys.sort();
+
+    println!(
+        "{:<15} {:<17} {:>7} {:>8} {:>7} {:>10} {:<14} {:<14} VENDOR",
+        "IP",
+        "MAC",
+        "PACKETS",
+        "REQUESTS",
+        "REPLIES",
+        "GRATUITOUS",
+        "FIRST SEEN",
+        "LAST SEEN"
+    );
+    for key in keys {
+        let (ip, mac) = key;
+        let entry = &hosts[key];
+        println!(
+            "{:<15} {:<17} {:>7} {:>8} {:>7} {:>10} {:<14} {:<14} {}",
+            ip.to_string(),
+            mac.to_string(),
+            entry.packets(),
+            entry.requests,
+            entry.replies,
+            entry.gratuitous,
+            format_timestamp(entry.first_seen),
+            format_timestamp(entry.last_seen),
+            oui_db.describe(*mac)
+        );
+    }
+
+    eprintln!("{} hosts seen", hosts.len());
+}
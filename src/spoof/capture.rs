@@ -0,0 +1,64 @@
+use super::Addrs;
+use crate::pcap::PcapWriter;
+use pnet::{packet::ethernet::EthernetPacket, util::MacAddr};
+use std::collections::HashSet;
+use std::sync::{Mutex, RwLock};
+
+/// Writes to a pcap the frames sent by or to the victims and the
+/// impersonated hosts. The frames sent by the attacker are skipped, since the
+/// relayed (or kernel forwarded) ones are copies of the frames already
+/// captured when they were received.
+pub struct Capture {
+    writer: Mutex<PcapWriter>,
+    attacker_mac: MacAddr,
+    hosts_addr: Vec<Addrs>,
+    macs: RwLock<HashSet<MacAddr>>,
+}
+
+impl Capture {
+    pub fn new(
+        writer: PcapWriter,
+        attacker_mac: MacAddr,
+        hosts_addr: Vec<Addrs>,
+        victims_addr: &[Addrs],
+    ) -> Self {
+        let capture = Self {
+            writer: Mutex::new(writer),
+            attacker_mac,
+            hosts_addr,
+            macs: RwLock::new(HashSet::new()),
+        };
+        capture.update(victims_addr);
+        return capture;
+    }
+
+    /// Updates the captured MACs when the victims change.
+    pub fn update(&self, victims_addr: &[Addrs]) {
+        let mut macs = self.macs.write().unwrap();
+        macs.clear();
+        for addr in self.hosts_addr.iter().chain(victims_addr.iter()) {
+            macs.insert(addr.mac);
+        }
+    }
+
+    pub fn capture(&self, buf: &[u8]) -> Result<(), String> {
+        let ether = match EthernetPacket::new(buf) {
+            Some(ether) => ether,
+            None => return Ok(()),
+        };
+        if ether.get_source() == self.attacker_mac {
+            return Ok(());
+        }
+
+        {
+            let macs = self.macs.read().unwrap();
+            if !macs.contains(&ether.get_source())
+                && !macs.contains(&ether.get_destination())
+            {
+                return Ok(());
+            }
+        }
+
+        return self.writer.lock().unwrap().write(buf);
+    }
+}
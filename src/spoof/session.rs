@@ -0,0 +1,343 @@
+use super::targets::Impersonated;
+use super::Addrs;
+use crate::args::spoof::Method;
+use crate::forward::Sysctls;
+use log::warn;
+use pnet::util::MacAddr;
+use std::fs;
+use std::net::Ipv4Addr;
+
+/// Addresses and settings of a spoofing, saved to a file while it runs so
+/// the victims can be recovered with the `recover` command if the process
+/// dies without recovering them.
+///
+/// The file is a list of lines with a key and its values:
+///
+/// ```text
+/// iface eth0
+/// method reply
+/// bidirectional
+/// sysctl net.ipv4.ip_forward 0
+/// attacker 192.168.100.3 52:54:00:0b:75:57
+/// host 192.168.100.2 52:54:00:88:80:0c
+/// victim 192.168.100.7 52:54:00:a4:8c:f2
+/// ```
+pub struct Session {
+    pub iface: String,
+    pub method: Method,
+    pub bidirectional: bool,
+    pub mesh: bool,
+    pub hosts_addr: Vec<Addrs>,
+    pub victims_addr: Vec<Addrs>,
+    /// Addresses that receive the traffic of the victims.
+    pub attacker_addr: Addrs,
+    /// Values of the sysctls before the spoofing, if they were changed.
+    pub sysctls: Option<Sysctls>,
+}
+
+impl Session {
+    pub fn impersonated(&self) -> Impersonated {
+        if self.mesh {
+            return Impersonated::Mesh;
+        }
+        return Impersonated::Hosts(self.hosts_addr.clone());
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Error reading session {}: {}", path, e))?;
+        return Self::parse(&content)
+            .map_err(|e| format!("Error parsing session {}: {}", path, e));
+    }
+
+    fn parse(content: &str) -> Result<Self, String> {
+        let mut iface = None;
+        let mut attacker_addr = None;
+        let mut sysctls = String::new();
+        let mut session = Self {
+            iface: String::new(),
+            method: Method::Reply,
+            bidirectional: false,
+            mesh: false,
+            hosts_addr: Vec::new(),
+            victims_addr: Vec::new(),
+            attacker_addr: Addrs::new(Ipv4Addr::UNSPECIFIED, MacAddr::zero()),
+            sysctls: None,
+        };
+
+        for (i, line) in content.lines().enumerate() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let invalid_line = || format!("Invalid line {}: {}", i + 1, line);
+
+            match fields.as_slice() {
+                [] => {}
+                [key, ..] if key.starts_with('#') => {}
+                ["iface", name] => iface = Some(name.to_string()),
+                ["method", method] => {
+                    session.method =
+                        parse_method(method).ok_or_else(invalid_line)?
+                }
+                ["bidirectional"] => session.bidirectional = true,
+                ["mesh"] => session.mesh = true,
+                ["sysctl", name, value] => {
+                    sysctls.push_str(&format!("{} {}\n", name, value))
+                }
+                ["attacker", ip, mac] => {
+                    attacker_addr =
+                        Some(parse_addrs(ip, mac).ok_or_else(invalid_line)?)
+                }
+                ["host", ip, mac] => session
+                    .hosts_addr
+                    .push(parse_addrs(ip, mac).ok_or_else(invalid_line)?),
+                ["victim", ip, mac] => session
+                    .victims_addr
+                    .push(parse_addrs(ip, mac).ok_or_else(invalid_line)?),
+                _ => return Err(invalid_line()),
+            }
+        }
+
+        session.iface = iface.ok_or("Missing iface")?;
+        session.attacker_addr = attacker_addr.ok_or("Missing attacker")?;
+        if !sysctls.is_empty() {
+            session.sysctls = Some(Sysctls::parse(&sysctls)?);
+        }
+        return Ok(session);
+    }
+
+    /// Writes the session to a temporary file which is moved to the path
+    /// then, so the file is never left half written.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let tmp_path = format!("{}.tmp", path);
+        fs::write(&tmp_path, self.format())
+            .and_then(|_| fs::rename(&tmp_path, path))
+            .map_err(|e| format!("Error saving session {}: {}", path, e))?;
+
+        return Ok(());
+    }
+
+    fn format(&self) -> String {
+        let mut content = String::from("# arplayer spoof session\n");
+        content.push_str(&format!("iface {}\n", self.iface));
+        content.push_str(&format!("method {}\n", method_name(self.method)));
+        if self.bidirectional {
+            content.push_str("bidirectional\n");
+        }
+        if self.mesh {
+            content.push_str("mesh\n");
+        }
+        if let Some(sysctls) = &self.sysctls {
+            for (name, value) in sysctls.iter() {
+                content.push_str(&format!("sysctl {} {}\n", name, value));
+            }
+        }
+        content.push_str(&format!(
+            "attacker {} {}\n",
+            self.attacker_addr.ip, self.attacker_addr.mac
+        ));
+        for host_addr in self.hosts_addr.iter() {
+            content.push_str(&format!(
+                "host {} {}\n",
+                host_addr.ip, host_addr.mac
+            ));
+        }
+        for victim_addr in self.victims_addr.iter() {
+            content.push_str(&format!(
+                "victim {} {}\n",
+                victim_addr.ip, victim_addr.mac
+            ));
+        }
+
+        return content;
+    }
+}
+
+/// Session saved in a file, which is updated when the victims change.
+pub struct SessionFile {
+    path: String,
+    session: Session,
+}
+
+impl SessionFile {
+    pub fn create(path: String, session: Session) -> Result<Self, String> {
+        session.save(&path)?;
+        return Ok(Self { path, session });
+    }
+
+    pub fn path(&self) -> &str {
+        return &self.path;
+    }
+
+    pub fn update_victims(&mut self, victims_addr: &[Addrs]) {
+        self.session.victims_addr = victims_addr.to_vec();
+        if let Err(e) = self.session.save(&self.path) {
+            warn!("{}", e);
+        }
+    }
+
+    pub fn remove(self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("Error removing session {}: {}", self.path, e);
+        }
+    }
+}
+
+/// Default path of the session file of the interface, in the current
+/// directory, since the temporary directory may not survive a reboot.
+pub fn default_path(iface_name: &str) -> String {
+    return format!("arplayer-{}.session", iface_name);
+}
+
+fn parse_addrs(ip: &str, mac: &str) -> Option<Addrs> {
+    let ip: Ipv4Addr = ip.parse().ok()?;
+    let mac = mac.parse().ok()?;
+    return Some(Addrs::new(ip, mac));
+}
+
+fn parse_method(method: &str) -> Option<Method> {
+    return match method {
+        "reply" => Some(Method::Reply),
+        "request" => Some(Method::Request),
+        "gratuitous-request" => Some(Method::GratuitousRequest),
+        "gratuitous-reply" => Some(Method::GratuitousReply),
+        "mixed" => Some(Method::Mixed),
+        _ => None,
+    };
+}
+
+fn method_name(method: Method) -> &'static str {
+    return match method {
+        Method::Reply => "reply",
+        Method::Request => "request",
+        Method::GratuitousRequest => "gratuitous-request",
+        Method::GratuitousReply => "gratuitous-reply",
+        Method::Mixed => "mixed",
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    fn addrs(ip: &str, mac: &str) -> Addrs {
+        return parse_addrs(ip, mac).unwrap();
+    }
+
+    fn ips(addrs: &[Addrs]) -> Vec<(Ipv4Addr, MacAddr)> {
+        return addrs.iter().map(|addr| (addr.ip, addr.mac)).collect();
+    }
+
+    fn session() -> Session {
+        return Session {
+            iface: "eth0".to_string(),
+            method: Method::GratuitousReply,
+            bidirectional: true,
+            mesh: false,
+            hosts_addr: vec![addrs("192.168.100.2", "52:54:00:88:80:0c")],
+            victims_addr: vec![
+                addrs("192.168.100.7", "52:54:00:a4:8c:f2"),
+                addrs("192.168.100.9", "52:54:00:5b:49:5d"),
+            ],
+            attacker_addr: addrs("192.168.100.3", "52:54:00:0b:75:57"),
+            sysctls: Some(
+                Sysctls::parse(
+                    "net.ipv4.ip_forward 0\nnet.ipv4.conf.eth0.rp_filter 2\n",
+                )
+                .unwrap(),
+            ),
+        };
+    }
+
+    fn assert_same(loaded: &Session, saved: &Session) {
+        assert_eq!(loaded.iface, saved.iface);
+        assert_eq!(method_name(loaded.method), method_name(saved.method));
+        assert_eq!(loaded.bidirectional, saved.bidirectional);
+        assert_eq!(loaded.mesh, saved.mesh);
+        assert_eq!(ips(&loaded.hosts_addr), ips(&saved.hosts_addr));
+        assert_eq!(ips(&loaded.victims_addr), ips(&saved.victims_addr));
+        assert_eq!(ips(&[loaded.attacker_addr]), ips(&[saved.attacker_addr]));
+        assert_eq!(
+            loaded.sysctls.as_ref().map(|s| s.iter().cloned().collect()),
+            saved
+                .sysctls
+                .as_ref()
+                .map(|s| s.iter().cloned().collect::<Vec<_>>())
+        );
+    }
+
+    #[test]
+    fn saves_and_loads_session() {
+        let saved = session();
+        let path = env::temp_dir()
+            .join(format!("arplayer-test-{}.session", process::id()));
+        let path = path.to_str().unwrap();
+
+        saved.save(path).unwrap();
+        let loaded = Session::load(path);
+        fs::remove_file(path).unwrap();
+
+        assert_same(&loaded.unwrap(), &saved);
+    }
+
+    #[test]
+    fn round_trips_mesh_without_sysctls() {
+        let mut saved = session();
+        saved.mesh = true;
+        saved.bidirectional = false;
+        saved.hosts_addr.clear();
+        saved.sysctls = None;
+        saved.method = Method::Mixed;
+
+        let loaded = Session::parse(&saved.format()).unwrap();
+        assert_same(&loaded, &saved);
+        assert!(matches!(loaded.impersonated(), Impersonated::Mesh));
+    }
+
+    #[test]
+    fn round_trips_every_method() {
+        for name in [
+            "reply",
+            "request",
+            "gratuitous-request",
+            "gratuitous-reply",
+            "mixed",
+        ] {
+            let mut saved = session();
+            saved.method = parse_method(name).unwrap();
+            let loaded = Session::parse(&saved.format()).unwrap();
+            assert_eq!(method_name(loaded.method), name);
+        }
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let content = "# comment\n\niface eth1\n  \nattacker 10.0.0.1 00:11:22:33:44:55\n";
+        let session = Session::parse(content).unwrap();
+        assert_eq!(session.iface, "eth1");
+        assert_eq!(method_name(session.method), "reply");
+        assert!(session.victims_addr.is_empty());
+        assert!(session.sysctls.is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_sessions() {
+        let attacker = "attacker 10.0.0.1 00:11:22:33:44:55\n";
+        assert!(Session::parse(attacker).is_err());
+        assert!(Session::parse("iface eth0\n").is_err());
+
+        let invalid = [
+            "method arp\n",
+            "victim 10.0.0.300 00:11:22:33:44:55\n",
+            "victim 10.0.0.2 00:11:22:33:44\n",
+            "host 10.0.0.2\n",
+            "unknown key\n",
+            "sysctl net.ipv4.ip_forward\n",
+        ];
+        for line in invalid.iter() {
+            let content = format!("iface eth0\n{}{}", attacker, line);
+            let err = Session::parse(&content).err().unwrap();
+            assert!(err.starts_with("Invalid line 3"), "{}", err);
+        }
+    }
+}
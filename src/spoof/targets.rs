@@ -0,0 +1,60 @@
+use super::Addrs;
+
+/// Hosts whose IPs are impersonated to the victims.
+pub enum Impersonated {
+    /// The victims are told that the IPs of these hosts are at the attacker
+    /// MAC (and these hosts about the victims, in bidirectional mode).
+    Hosts(Vec<Addrs>),
+    /// Every victim is told that the IPs of the other victims are at the
+    /// attacker MAC.
+    Mesh,
+}
+
+/// The target is told about the IP of the impersonated host.
+#[derive(Clone, Copy)]
+pub struct Pair {
+    pub target: Addrs,
+    pub impersonated: Addrs,
+}
+
+impl Impersonated {
+    /// Returns every pair to spoof for the current victims.
+    pub fn pairs(
+        &self,
+        victims_addr: &[Addrs],
+        bidirectional: bool,
+    ) -> Vec<Pair> {
+        let mut pairs = Vec::new();
+        match self {
+            Self::Hosts(hosts_addr) => {
+                for victim_addr in victims_addr.iter() {
+                    for host_addr in hosts_addr.iter() {
+                        pairs.push(Pair {
+                            target: *victim_addr,
+                            impersonated: *host_addr,
+                        });
+                        if bidirectional {
+                            pairs.push(Pair {
+                                target: *host_addr,
+                                impersonated: *victim_addr,
+                            });
+                        }
+                    }
+                }
+            }
+            Self::Mesh => {
+                for target_addr in victims_addr.iter() {
+                    for victim_addr in victims_addr.iter() {
+                        if target_addr.ip != victim_addr.ip {
+                            pairs.push(Pair {
+                                target: *target_addr,
+                                impersonated: *victim_addr,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        return pairs;
+    }
+}
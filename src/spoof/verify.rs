@@ -0,0 +1,170 @@
+use super::targets::Pair;
+use super::{send_round, Addrs, LISTEN_TIMEOUT};
+use crate::args::spoof::Method;
+use crate::arp::new_ether_channel;
+use crate::oui::OuiDb;
+use log::{debug, warn};
+use pnet::{
+    datalink::{Config, NetworkInterface},
+    packet::{
+        arp::ArpPacket,
+        ethernet::{EtherTypes, EthernetPacket},
+        ipv4::Ipv4Packet,
+        Packet,
+    },
+    util::MacAddr,
+};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::Ipv4Addr;
+use std::time::{Duration, Instant};
+
+#[derive(PartialEq)]
+enum Status {
+    Confirmed,
+    Unconfirmed,
+    Poisoned,
+}
+
+/// Last frames sent by a target to the attacker and to the real MACs of the
+/// hosts it was spoofed about.
+struct TargetState {
+    addr: Addrs,
+    real_macs: Vec<MacAddr>,
+    last_poisoned: Option<Instant>,
+    last_recovered: Option<Instant>,
+}
+
+impl TargetState {
+    fn status(&self) -> Status {
+        return match (self.last_poisoned, self.last_recovered) {
+            (Some(poisoned), Some(recovered)) if recovered > poisoned => {
+                Status::Confirmed
+            }
+            (Some(_), _) => Status::Poisoned,
+            (None, Some(_)) => Status::Confirmed,
+            (None, None) => Status::Unconfirmed,
+        };
+    }
+}
+
+/// Listens the traffic of the targets after the recovery, and resends the
+/// correct mappings every delay to the ones that are not confirmed, until all
+/// of them are sending frames to the real MACs or the timeout expires. Then
+/// reports every target as confirmed, unconfirmed (not seen) or still
+/// poisoned (still sending frames to the attacker MAC).
+pub fn verify_recovery(
+    iface: &NetworkInterface,
+    pairs: &[Pair],
+    attacker_addr: &Addrs,
+    method: Method,
+    delay: Duration,
+    timeout: Duration,
+    oui_db: &OuiDb,
+) -> Result<(), String> {
+    let config = Config {
+        read_timeout: Some(LISTEN_TIMEOUT),
+        ..Default::default()
+    };
+    let (mut sender, mut receiver) = new_ether_channel(iface, config)?;
+
+    let mut states: HashMap<MacAddr, TargetState> = HashMap::new();
+    for pair in pairs.iter() {
+        let state =
+            states
+                .entry(pair.target.mac)
+                .or_insert_with(|| TargetState {
+                    addr: pair.target,
+                    real_macs: Vec::new(),
+                    last_poisoned: None,
+                    last_recovered: None,
+                });
+        state.real_macs.push(pair.impersonated.mac);
+    }
+
+    eprintln!(
+        "Verifying recovery of {} hosts for {} seconds",
+        states.len(),
+        timeout.as_secs()
+    );
+
+    let start = Instant::now();
+    let mut last_sent = Instant::now();
+    while start.elapsed() < timeout {
+        if states.values().all(|s| s.status() == Status::Confirmed) {
+            break;
+        }
+
+        if last_sent.elapsed() >= delay {
+            let pending: Vec<Pair> = pairs
+                .iter()
+                .filter(|p| states[&p.target.mac].status() != Status::Confirmed)
+                .cloned()
+                .collect();
+            send_round(&mut sender, method, &pending, None)?;
+            last_sent = Instant::now();
+        }
+
+        let buf = match receiver.next() {
+            Ok(buf) => buf,
+            Err(e) if e.kind() == ErrorKind::TimedOut => continue,
+            Err(e) => return Err(format!("Error receiving packet: {}", e)),
+        };
+
+        let ether = match EthernetPacket::new(buf) {
+            Some(ether) => ether,
+            None => continue,
+        };
+        let state = match states.get_mut(&ether.get_source()) {
+            Some(state) => state,
+            None => continue,
+        };
+
+        let destination = ether.get_destination();
+        if destination == attacker_addr.mac {
+            if is_poisoned_frame(&ether, attacker_addr.ip) {
+                debug!("{} still sends frames to attacker", state.addr.ip);
+                state.last_poisoned = Some(Instant::now());
+            }
+        } else if state.real_macs.contains(&destination) {
+            state.last_recovered = Some(Instant::now());
+        }
+    }
+
+    let mut states: Vec<&TargetState> = states.values().collect();
+    states.sort_by_key(|s| s.addr.ip);
+    for state in states {
+        let desc = format!(
+            "{} ({}, {})",
+            state.addr.ip,
+            state.addr.mac,
+            oui_db.describe(state.addr.mac)
+        );
+        match state.status() {
+            Status::Confirmed => eprintln!("Recovered {}: confirmed", desc),
+            Status::Unconfirmed => {
+                eprintln!("Recovered {}: unconfirmed, no traffic seen", desc)
+            }
+            Status::Poisoned => warn!("{} is still poisoned", desc),
+        }
+    }
+
+    return Ok(());
+}
+
+/// Checks if the frame sent to the attacker MAC is for another IP, since the
+/// frames for the attacker IP are legit. Only IPv4 and ARP are affected by
+/// the poisoning.
+fn is_poisoned_frame(ether: &EthernetPacket, attacker_ip: Ipv4Addr) -> bool {
+    match ether.get_ethertype() {
+        EtherTypes::Ipv4 => match Ipv4Packet::new(ether.payload()) {
+            Some(ip) => ip.get_destination() != attacker_ip,
+            None => false,
+        },
+        EtherTypes::Arp => match ArpPacket::new(ether.payload()) {
+            Some(arp) => arp.get_target_proto_addr() != attacker_ip,
+            None => false,
+        },
+        _ => false,
+    }
+}
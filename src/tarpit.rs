@@ -0,0 +1,244 @@
+use crate::reply::Request;
+use log::{debug, info};
+use pnet::util::MacAddr;
+use std::collections::{HashMap, HashSet};
+use std::net::Ipv4Addr;
+use std::time::{Duration, Instant};
+
+struct Pending {
+    since: Instant,
+    requests: u32,
+    requesters: Vec<Request>,
+}
+
+/// Time in which the requests for an IP must be repeated to trap it. The
+/// pending IPs that are not requested enough within it are forgotten.
+const REQUEST_WINDOW: Duration = Duration::from_secs(30);
+
+/// State of the requested IPs, to only answer for the ones that nobody else
+/// claims (like LaBrea). The requests for an IP are kept pending until the
+/// max number of requests is reached without a genuine reply (the requesters
+/// only repeat them when nobody answers) or, if given, the grace period
+/// expires. Then the IP is trapped and its requests are answered, until its
+/// real owner shows up. The owner is forgotten when it is not seen for the
+/// owner timeout, so the IPs of the hosts that leave can be trapped again.
+///
+/// The owners reply to the requester alone, so in switched networks their
+/// replies are usually not seen and the grace period would trap live hosts.
+pub struct Tarpit {
+    grace: Option<Duration>,
+    max_requests: u32,
+    owner_timeout: Duration,
+    /// MACs used by us, whose packets never come from an owner.
+    own_macs: HashSet<MacAddr>,
+    pending: HashMap<Ipv4Addr, Pending>,
+    trapped: HashSet<Ipv4Addr>,
+    /// Owned IPs, with the last time their owner was seen.
+    owned: HashMap<Ipv4Addr, Instant>,
+}
+
+impl Tarpit {
+    pub fn new(
+        grace: Option<Duration>,
+        max_requests: u32,
+        owner_timeout: Duration,
+        own_macs: HashSet<MacAddr>,
+    ) -> Self {
+        return Self {
+            grace,
+            max_requests,
+            owner_timeout,
+            own_macs,
+            pending: HashMap::new(),
+            trapped: HashSet::new(),
+            owned: HashMap::new(),
+        };
+    }
+
+    /// Registers the sender of an ARP packet of other host, which is the real
+    /// owner of the IP, so it is not answered anymore. The packets sent with
+    /// our own MACs, like our replies, are ignored.
+    pub fn see_owner(&mut self, ip: Ipv4Addr, mac: MacAddr) {
+        // probes are sent before having an IP
+        if ip.is_unspecified() || self.own_macs.contains(&mac) {
+            return;
+        }
+        if self.owned.insert(ip, Instant::now()).is_some() {
+            return;
+        }
+
+        self.pending.remove(&ip);
+        if self.trapped.remove(&ip) {
+            info!("{} claimed by {}, stop answering it", ip, mac);
+        } else {
+            debug!("{} owned by {}", ip, mac);
+        }
+    }
+
+    /// Registers a request and returns the ones to answer now.
+    pub fn on_request(&mut self, request: Request) -> Vec<Request> {
+        let ip = request.target_ip;
+        if let Some(seen) = self.owned.get(&ip) {
+            if seen.elapsed() < self.owner_timeout {
+                return Vec::new();
+            }
+            debug!("Owner of {} not seen for {:?}", ip, seen.elapsed());
+            self.owned.remove(&ip);
+        }
+        if self.trapped.contains(&ip) {
+            return vec![request];
+        }
+
+        let pending = self.pending.entry(ip).or_insert_with(|| Pending {
+            since: Instant::now(),
+            requests: 0,
+            requesters: Vec::new(),
+        });
+        pending.requests += 1;
+        debug!(
+            "Request {} for {} from {} unanswered",
+            pending.requests, ip, request.sender_ip
+        );
+        if !pending.requesters.iter().any(|r| {
+            r.sender_ip == request.sender_ip
+                && r.sender_mac == request.sender_mac
+        }) {
+            pending.requesters.push(request);
+        }
+
+        if pending.requests >= self.max_requests {
+            return self.trap(ip);
+        }
+        return Vec::new();
+    }
+
+    /// Traps the IPs whose grace period expired and returns their pending
+    /// requests to answer. The IPs not requested enough within the request
+    /// window are forgotten.
+    pub fn expired(&mut self) -> Vec<Request> {
+        let grace = self.grace;
+        let ips: Vec<Ipv4Addr> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| match grace {
+                Some(grace) => pending.since.elapsed() >= grace,
+                None => false,
+            })
+            .map(|(ip, _)| *ip)
+            .collect();
+
+        self.pending
+            .retain(|_, pending| pending.since.elapsed() < REQUEST_WINDOW);
+
+        return ips.into_iter().flat_map(|ip| self.trap(ip)).collect();
+    }
+
+    fn trap(&mut self, ip: Ipv4Addr) -> Vec<Request> {
+        let pending = self.pending.remove(&ip).unwrap();
+        self.trapped.insert(ip);
+        info!(
+            "Nobody answered {} requests for {}, trapping it",
+            pending.requests, ip
+        );
+        return pending.requesters;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OWN_MAC: MacAddr = MacAddr(0x52, 0x54, 0, 0, 0, 1);
+    const OWNER_MAC: MacAddr = MacAddr(0x52, 0x54, 0, 0, 0, 2);
+
+    fn tarpit(grace: Option<Duration>, max_requests: u32) -> Tarpit {
+        return Tarpit::new(
+            grace,
+            max_requests,
+            Duration::from_secs(300),
+            HashSet::from([OWN_MAC]),
+        );
+    }
+
+    fn request(sender: u8, target: u8) -> Request {
+        return Request {
+            sender_ip: Ipv4Addr::new(10, 0, 0, sender),
+            sender_mac: MacAddr(0x52, 0x54, 0, 0, 1, sender),
+            target_ip: Ipv4Addr::new(10, 0, 0, target),
+        };
+    }
+
+    #[test]
+    fn traps_after_grace_period() {
+        let mut tarpit = tarpit(Some(Duration::ZERO), 10);
+        assert!(tarpit.on_request(request(1, 9)).is_empty());
+        assert!(tarpit.on_request(request(2, 9)).is_empty());
+        // repeated requesters are answered once
+        assert!(tarpit.on_request(request(1, 9)).is_empty());
+
+        let answered = tarpit.expired();
+        assert_eq!(answered.len(), 2);
+        assert!(tarpit.expired().is_empty());
+        assert_eq!(tarpit.on_request(request(3, 9)).len(), 1);
+    }
+
+    #[test]
+    fn only_traps_after_max_requests_without_grace() {
+        let mut tarpit = tarpit(None, 3);
+        assert!(tarpit.on_request(request(1, 9)).is_empty());
+        assert!(tarpit.expired().is_empty());
+        assert!(tarpit.on_request(request(1, 9)).is_empty());
+        assert!(tarpit.expired().is_empty());
+        assert_eq!(tarpit.on_request(request(1, 9)).len(), 1);
+    }
+
+    #[test]
+    fn traps_after_max_requests() {
+        let mut tarpit = tarpit(Some(Duration::from_secs(300)), 3);
+        assert!(tarpit.on_request(request(1, 9)).is_empty());
+        assert!(tarpit.on_request(request(1, 9)).is_empty());
+        assert_eq!(tarpit.on_request(request(1, 9)).len(), 1);
+        assert!(tarpit.expired().is_empty());
+        assert_eq!(tarpit.on_request(request(2, 9)).len(), 1);
+    }
+
+    #[test]
+    fn stops_answering_owned_ips() {
+        let mut tarpit = tarpit(Some(Duration::ZERO), 1);
+        let ip = Ipv4Addr::new(10, 0, 0, 9);
+
+        // owned before being requested
+        tarpit.see_owner(Ipv4Addr::new(10, 0, 0, 8), OWNER_MAC);
+        assert!(tarpit.on_request(request(1, 8)).is_empty());
+        assert!(tarpit.expired().is_empty());
+
+        // owned once trapped
+        assert_eq!(tarpit.on_request(request(1, 9)).len(), 1);
+        tarpit.see_owner(ip, OWNER_MAC);
+        assert!(tarpit.on_request(request(1, 9)).is_empty());
+
+        // probes don't claim any IP
+        tarpit.see_owner(Ipv4Addr::UNSPECIFIED, OWNER_MAC);
+        assert_eq!(tarpit.on_request(request(1, 7)).len(), 1);
+    }
+
+    #[test]
+    fn ignores_own_macs() {
+        let mut tarpit = tarpit(Some(Duration::ZERO), 1);
+        assert_eq!(tarpit.on_request(request(1, 9)).len(), 1);
+        tarpit.see_owner(Ipv4Addr::new(10, 0, 0, 9), OWN_MAC);
+        assert_eq!(tarpit.on_request(request(1, 9)).len(), 1);
+    }
+
+    #[test]
+    fn forgets_owners_after_timeout() {
+        let mut tarpit = Tarpit::new(
+            Some(Duration::ZERO),
+            1,
+            Duration::ZERO,
+            HashSet::from([OWN_MAC]),
+        );
+        tarpit.see_owner(Ipv4Addr::new(10, 0, 0, 9), OWNER_MAC);
+        assert_eq!(tarpit.on_request(request(1, 9)).len(), 1);
+    }
+}
@@ -0,0 +1,268 @@
+use super::{
+    parse_ip_range, BroadcastValidator, DstIpValidator, DstMacValidator,
+    DstNetValidator, DstRangeValidator, EtherDstValidator, EtherSrcValidator,
+    GratuitousValidator, MacField, MacPrefixValidator, OpcodeValidator,
+    SenderMismatchValidator, SrcIpValidator, SrcMacValidator, SrcNetValidator,
+    SrcRangeValidator, Validator,
+};
+use crate::expr::{self, Builder, Tokens};
+use pnet::{
+    ipnetwork::Ipv4Network,
+    packet::arp::{ArpOperation, ArpOperations},
+    util::MacAddr,
+};
+use std::{collections::HashSet, net::Ipv4Addr};
+
+/// Compiles a filter expression into a validator of ARP packets. The
+/// primitives are:
+///
+/// ```text
+/// src ip <ip|ip/prefix|first-last>
+/// dst ip <ip|ip/prefix|first-last>
+/// src mac <mac|prefix:*>
+/// dst mac <mac|prefix:*>
+/// ether src <mac|prefix:*>
+/// ether dst <mac|prefix:*>
+/// opcode <request|reply|number>
+/// request | reply | broadcast | mismatch | gratuitous
+/// ```
+///
+/// `src` and `dst` refer to the sender and target of the ARP packet, `ether`
+/// to the addresses of the Ethernet frame, `broadcast` matches the frames sent
+/// to the broadcast MAC, `mismatch` the packets whose sender MAC is not the
+/// Ethernet source, and `gratuitous` the ones whose sender IP is the target IP.
+///
+/// They can be combined with `and` (`&&`), `or` (`||`), `not` (`!`) and
+/// parentheses, for example
+/// `src ip 10.0.0.0/24 and (dst ip 10.0.0.1 or src mac 52:54:00:*)`.
+pub fn parse_filter(expr: &str) -> Result<Validator, String> {
+    return expr::parse(expr, &ValidatorBuilder);
+}
+
+/// Builds the validator from the primitives of the expression.
+struct ValidatorBuilder;
+
+impl Builder for ValidatorBuilder {
+    type Filter = Validator;
+
+    fn primitive(
+        &self,
+        token: &str,
+        tokens: &mut Tokens,
+    ) -> Result<Validator, String> {
+        match token {
+            "src" => {
+                let kind = tokens.next()?;
+                let value = tokens.next()?;
+                match kind.as_str() {
+                    "ip" => return src_ip(&value),
+                    "mac" => return mac(MacField::Sender, &value),
+                    _ => {
+                        return Err(format!("Unknown primitive 'src {}'", kind))
+                    }
+                }
+            }
+            "dst" => {
+                let kind = tokens.next()?;
+                let value = tokens.next()?;
+                match kind.as_str() {
+                    "ip" => return dst_ip(&value),
+                    "mac" => return mac(MacField::Target, &value),
+                    _ => {
+                        return Err(format!("Unknown primitive 'dst {}'", kind))
+                    }
+                }
+            }
+            "ether" => {
+                let dir = tokens.next()?;
+                let value = tokens.next()?;
+                match dir.as_str() {
+                    "src" => return mac(MacField::EtherSrc, &value),
+                    "dst" => return mac(MacField::EtherDst, &value),
+                    _ => {
+                        return Err(format!(
+                            "Unknown primitive 'ether {}'",
+                            dir
+                        ))
+                    }
+                }
+            }
+            "opcode" => {
+                let value = tokens.next()?;
+                return opcode(&value);
+            }
+            "request" | "reply" => return opcode(token),
+            "broadcast" => return Ok(BroadcastValidator::new()),
+            "mismatch" => return Ok(SenderMismatchValidator::new()),
+            "gratuitous" => return Ok(GratuitousValidator::new()),
+            _ => return Err(format!("Unknown primitive '{}'", token)),
+        }
+    }
+
+    fn and(&self, v1: Validator, v2: Validator) -> Validator {
+        return v1 & v2;
+    }
+
+    fn or(&self, v1: Validator, v2: Validator) -> Validator {
+        return v1 | v2;
+    }
+
+    fn not(&self, v: Validator) -> Validator {
+        return !v;
+    }
+}
+
+fn src_ip(value: &str) -> Result<Validator, String> {
+    if let Ok(ip) = value.parse::<Ipv4Addr>() {
+        return Ok(SrcIpValidator::new(HashSet::from([ip])));
+    }
+    if let Ok(net) = value.parse::<Ipv4Network>() {
+        return Ok(SrcNetValidator::new(vec![net]));
+    }
+    let range = parse_ip_range(value)
+        .ok_or_else(|| format!("Invalid IP '{}'", value))?;
+    return Ok(SrcRangeValidator::new(vec![range]));
+}
+
+fn dst_ip(value: &str) -> Result<Validator, String> {
+    if let Ok(ip) = value.parse::<Ipv4Addr>() {
+        return Ok(DstIpValidator::new(HashSet::from([ip])));
+    }
+    if let Ok(net) = value.parse::<Ipv4Network>() {
+        return Ok(DstNetValidator::new(vec![net]));
+    }
+    let range = parse_ip_range(value)
+        .ok_or_else(|| format!("Invalid IP '{}'", value))?;
+    return Ok(DstRangeValidator::new(vec![range]));
+}
+
+fn opcode(value: &str) -> Result<Validator, String> {
+    let operation = match value {
+        "request" => ArpOperations::Request,
+        "reply" => ArpOperations::Reply,
+        _ => ArpOperation::new(
+            value
+                .parse()
+                .map_err(|_| format!("Invalid opcode '{}'", value))?,
+        ),
+    };
+    return Ok(OpcodeValidator::new(operation));
+}
+
+/// Parses a MAC, or a prefix of it followed by `*`, like `52:54:00:*`.
+fn mac(field: MacField, value: &str) -> Result<Validator, String> {
+    let prefix = match value.strip_suffix('*') {
+        Some(prefix) => prefix.trim_end_matches(':'),
+        None => {
+            let mac = value
+                .parse::<MacAddr>()
+                .map_err(|_| format!("Invalid MAC '{}'", value))?;
+            let macs = HashSet::from([mac]);
+            return Ok(match field {
+                MacField::Sender => SrcMacValidator::new(macs),
+                MacField::Target => DstMacValidator::new(macs),
+                MacField::EtherSrc => EtherSrcValidator::new(macs),
+                MacField::EtherDst => EtherDstValidator::new(macs),
+            });
+        }
+    };
+
+    let mut bytes = Vec::new();
+    if !prefix.is_empty() {
+        for byte in prefix.split(':') {
+            let byte = u8::from_str_radix(byte, 16)
+                .map_err(|_| format!("Invalid MAC prefix '{}'", value))?;
+            bytes.push(byte);
+        }
+    }
+    if bytes.len() > 6 {
+        return Err(format!("Invalid MAC prefix '{}'", value));
+    }
+
+    return Ok(MacPrefixValidator::new(field, bytes));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::arp_frame;
+    use super::*;
+
+    /// Request of 10.0.0.1 for 10.0.0.2, broadcast from 52:54:00:00:00:01.
+    fn request() -> Vec<u8> {
+        return arp_frame(
+            ("10.0.0.1", "52:54:00:00:00:01"),
+            ("10.0.0.2", "00:00:00:00:00:00"),
+            "ff:ff:ff:ff:ff:ff",
+        );
+    }
+
+    fn matches(expr: &str) -> bool {
+        return parse_filter(expr).unwrap().is_valid(&request());
+    }
+
+    #[test]
+    fn matches_primitives() {
+        assert!(matches("src ip 10.0.0.1"));
+        assert!(matches("src ip 10.0.0.0/24"));
+        assert!(matches("dst ip 10.0.0.1-10.0.0.9"));
+        assert!(!matches("dst ip 10.0.0.1"));
+        assert!(matches("src mac 52:54:00:00:00:01"));
+        assert!(matches("src mac 52:54:00:*"));
+        assert!(matches("src mac *"));
+        assert!(!matches("dst mac 52:54:*"));
+        assert!(matches("ether src 52:54:00:00:00:01"));
+        assert!(matches("ether dst ff:ff:ff:ff:ff:ff"));
+        assert!(matches("opcode 1"));
+        assert!(matches("request"));
+        assert!(!matches("reply"));
+        assert!(matches("broadcast"));
+        assert!(!matches("mismatch"));
+        assert!(!matches("gratuitous"));
+    }
+
+    #[test]
+    fn combines_with_precedence() {
+        assert!(matches("reply and gratuitous or request"));
+        assert!(!matches("reply and (gratuitous or request)"));
+        assert!(matches(
+            "src ip 10.0.0.0/24 and (dst ip 10.0.0.9 or src mac 52:54:00:*)"
+        ));
+        assert!(matches("not reply and not gratuitous"));
+        assert!(!matches("not (reply or request)"));
+    }
+
+    #[test]
+    fn negates_without_spaces() {
+        assert!(matches("!gratuitous"));
+        assert!(matches("not(src ip 10.0.0.9)"));
+        assert!(matches("!(reply||gratuitous)&&request"));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for expr in &[
+            "",
+            "foo",
+            "src",
+            "src ip",
+            "src port 80",
+            "src ip 10.0.0.256",
+            "dst ip 10.0.0.9-10.0.0.1",
+            "src mac 52:54:00",
+            "src mac 52:54:zz:*",
+            "src mac 52:54:00:00:00:00:00:*",
+            "ether host 52:54:00:00:00:01",
+            "opcode 70000",
+            "(request",
+            "request)",
+            "request and",
+            "request reply",
+        ] {
+            assert!(parse_filter(expr).is_err(), "{}", expr);
+        }
+        assert_eq!(
+            parse_filter("src port 80").err().unwrap(),
+            "Invalid filter 'src port 80': Unknown primitive 'src port'"
+        );
+    }
+}
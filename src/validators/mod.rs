@@ -1,11 +1,49 @@
-use pnet::{packet::arp::ArpPacket, util::MacAddr};
-use std::ops::{BitAnd, Not};
+// The constructors return the boxed `Validator`, so they can be combined
+// right away with `&`, `|` and `!`.
+#![allow(clippy::new_ret_no_self)]
+
+mod expr;
+
+pub use expr::parse_filter;
+
+use pnet::ipnetwork::Ipv4Network;
+use pnet::{
+    packet::{
+        arp::{ArpOperation, ArpPacket},
+        ethernet::{EtherTypes, EthernetPacket},
+        Packet,
+    },
+    util::MacAddr,
+};
+use std::ops::{BitAnd, BitOr, Not};
 use std::{collections::HashSet, net::Ipv4Addr};
 
 pub type Validator = Box<dyn ValidatorTrait>;
 
+/// Checks ARP packets, which are given with the Ethernet frame that carries
+/// them.
 pub trait ValidatorTrait: Sync + Send {
-    fn is_valid_arp(&self, arp_packet: &ArpPacket) -> bool;
+    fn is_valid_arp(
+        &self,
+        ether_packet: &EthernetPacket,
+        arp_packet: &ArpPacket,
+    ) -> bool;
+
+    /// Checks the ARP packet of the frame. Frames that don't carry one are not
+    /// valid.
+    fn is_valid(&self, buf: &[u8]) -> bool {
+        let ether = match EthernetPacket::new(buf) {
+            Some(ether) => ether,
+            None => return false,
+        };
+        if ether.get_ethertype() != EtherTypes::Arp {
+            return false;
+        }
+        return match ArpPacket::new(ether.payload()) {
+            Some(arp_packet) => self.is_valid_arp(&ether, &arp_packet),
+            None => false,
+        };
+    }
 }
 
 impl BitAnd for Validator {
@@ -27,6 +65,14 @@ impl BitAnd<Option<Validator>> for Validator {
     }
 }
 
+impl BitOr for Validator {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        return OrValidator::new(vec![self, rhs]);
+    }
+}
+
 impl Not for Validator {
     type Output = Self;
 
@@ -46,8 +92,12 @@ impl NotValidator {
 }
 
 impl ValidatorTrait for NotValidator {
-    fn is_valid_arp(&self, arp_packet: &ArpPacket) -> bool {
-        return !self.v_1.is_valid_arp(arp_packet);
+    fn is_valid_arp(
+        &self,
+        ether_packet: &EthernetPacket,
+        arp_packet: &ArpPacket,
+    ) -> bool {
+        return !self.v_1.is_valid_arp(ether_packet, arp_packet);
     }
 }
 
@@ -62,9 +112,13 @@ impl AndValidator {
 }
 
 impl ValidatorTrait for AndValidator {
-    fn is_valid_arp(&self, arp_packet: &ArpPacket) -> bool {
+    fn is_valid_arp(
+        &self,
+        ether_packet: &EthernetPacket,
+        arp_packet: &ArpPacket,
+    ) -> bool {
         for verificator in self.sub_vs.iter() {
-            if !verificator.is_valid_arp(arp_packet) {
+            if !verificator.is_valid_arp(ether_packet, arp_packet) {
                 return false;
             }
         }
@@ -72,6 +126,31 @@ impl ValidatorTrait for AndValidator {
     }
 }
 
+pub struct OrValidator {
+    sub_vs: Vec<Validator>,
+}
+
+impl OrValidator {
+    pub fn new(sub_vs: Vec<Validator>) -> Validator {
+        return Box::new(Self { sub_vs });
+    }
+}
+
+impl ValidatorTrait for OrValidator {
+    fn is_valid_arp(
+        &self,
+        ether_packet: &EthernetPacket,
+        arp_packet: &ArpPacket,
+    ) -> bool {
+        for verificator in self.sub_vs.iter() {
+            if verificator.is_valid_arp(ether_packet, arp_packet) {
+                return true;
+            }
+        }
+        return false;
+    }
+}
+
 pub struct SrcIpValidator {
     ips: HashSet<Ipv4Addr>,
 }
@@ -83,7 +162,11 @@ impl SrcIpValidator {
 }
 
 impl ValidatorTrait for SrcIpValidator {
-    fn is_valid_arp(&self, arp_packet: &ArpPacket) -> bool {
+    fn is_valid_arp(
+        &self,
+        _ether_packet: &EthernetPacket,
+        arp_packet: &ArpPacket,
+    ) -> bool {
         return self.ips.contains(&arp_packet.get_sender_proto_addr());
     }
 }
@@ -99,7 +182,11 @@ impl DstIpValidator {
 }
 
 impl ValidatorTrait for DstIpValidator {
-    fn is_valid_arp(&self, arp_packet: &ArpPacket) -> bool {
+    fn is_valid_arp(
+        &self,
+        _ether_packet: &EthernetPacket,
+        arp_packet: &ArpPacket,
+    ) -> bool {
         return self.ips.contains(&arp_packet.get_target_proto_addr());
     }
 }
@@ -120,7 +207,373 @@ impl SrcMacValidator {
 }
 
 impl ValidatorTrait for SrcMacValidator {
-    fn is_valid_arp(&self, arp_packet: &ArpPacket) -> bool {
+    fn is_valid_arp(
+        &self,
+        _ether_packet: &EthernetPacket,
+        arp_packet: &ArpPacket,
+    ) -> bool {
         return self.macs.contains(&arp_packet.get_sender_hw_addr());
     }
 }
+
+pub struct DstMacValidator {
+    macs: HashSet<MacAddr>,
+}
+
+impl DstMacValidator {
+    pub fn new(macs: HashSet<MacAddr>) -> Validator {
+        return Box::new(Self { macs });
+    }
+}
+
+impl ValidatorTrait for DstMacValidator {
+    fn is_valid_arp(
+        &self,
+        _ether_packet: &EthernetPacket,
+        arp_packet: &ArpPacket,
+    ) -> bool {
+        return self.macs.contains(&arp_packet.get_target_hw_addr());
+    }
+}
+
+pub struct EtherSrcValidator {
+    macs: HashSet<MacAddr>,
+}
+
+impl EtherSrcValidator {
+    pub fn new(macs: HashSet<MacAddr>) -> Validator {
+        return Box::new(Self { macs });
+    }
+}
+
+impl ValidatorTrait for EtherSrcValidator {
+    fn is_valid_arp(
+        &self,
+        ether_packet: &EthernetPacket,
+        _arp_packet: &ArpPacket,
+    ) -> bool {
+        return self.macs.contains(&ether_packet.get_source());
+    }
+}
+
+pub struct EtherDstValidator {
+    macs: HashSet<MacAddr>,
+}
+
+impl EtherDstValidator {
+    pub fn new(macs: HashSet<MacAddr>) -> Validator {
+        return Box::new(Self { macs });
+    }
+}
+
+impl ValidatorTrait for EtherDstValidator {
+    fn is_valid_arp(
+        &self,
+        ether_packet: &EthernetPacket,
+        _arp_packet: &ArpPacket,
+    ) -> bool {
+        return self.macs.contains(&ether_packet.get_destination());
+    }
+}
+
+/// MAC addresses of the ARP packet and its Ethernet frame.
+#[derive(Debug, Clone, Copy)]
+pub enum MacField {
+    Sender,
+    Target,
+    EtherSrc,
+    EtherDst,
+}
+
+impl MacField {
+    fn get(
+        &self,
+        ether_packet: &EthernetPacket,
+        arp_packet: &ArpPacket,
+    ) -> MacAddr {
+        return match self {
+            MacField::Sender => arp_packet.get_sender_hw_addr(),
+            MacField::Target => arp_packet.get_target_hw_addr(),
+            MacField::EtherSrc => ether_packet.get_source(),
+            MacField::EtherDst => ether_packet.get_destination(),
+        };
+    }
+}
+
+/// Accepts the packets whose MAC field starts with the given bytes.
+pub struct MacPrefixValidator {
+    field: MacField,
+    prefix: Vec<u8>,
+}
+
+impl MacPrefixValidator {
+    pub fn new(field: MacField, prefix: Vec<u8>) -> Validator {
+        return Box::new(Self { field, prefix });
+    }
+}
+
+impl ValidatorTrait for MacPrefixValidator {
+    fn is_valid_arp(
+        &self,
+        ether_packet: &EthernetPacket,
+        arp_packet: &ArpPacket,
+    ) -> bool {
+        let mac = self.field.get(ether_packet, arp_packet).octets();
+        return mac.starts_with(&self.prefix);
+    }
+}
+
+/// Accepts the frames sent to the broadcast MAC.
+pub struct BroadcastValidator {}
+
+impl BroadcastValidator {
+    pub fn new() -> Validator {
+        return Box::new(Self {});
+    }
+}
+
+impl ValidatorTrait for BroadcastValidator {
+    fn is_valid_arp(
+        &self,
+        ether_packet: &EthernetPacket,
+        _arp_packet: &ArpPacket,
+    ) -> bool {
+        return ether_packet.get_destination().is_broadcast();
+    }
+}
+
+pub struct OpcodeValidator {
+    operation: ArpOperation,
+}
+
+impl OpcodeValidator {
+    pub fn new(operation: ArpOperation) -> Validator {
+        return Box::new(Self { operation });
+    }
+}
+
+impl ValidatorTrait for OpcodeValidator {
+    fn is_valid_arp(
+        &self,
+        _ether_packet: &EthernetPacket,
+        arp_packet: &ArpPacket,
+    ) -> bool {
+        return arp_packet.get_operation() == self.operation;
+    }
+}
+
+/// Accepts the packets whose ARP sender MAC is not the Ethernet source, which
+/// are crafted or relayed by another host.
+pub struct SenderMismatchValidator {}
+
+impl SenderMismatchValidator {
+    pub fn new() -> Validator {
+        return Box::new(Self {});
+    }
+}
+
+impl ValidatorTrait for SenderMismatchValidator {
+    fn is_valid_arp(
+        &self,
+        ether_packet: &EthernetPacket,
+        arp_packet: &ArpPacket,
+    ) -> bool {
+        return ether_packet.get_source() != arp_packet.get_sender_hw_addr();
+    }
+}
+
+/// Accepts the gratuitous ARP packets, in which a host announces its own IP
+/// (the sender IP is the target IP).
+pub struct GratuitousValidator {}
+
+impl GratuitousValidator {
+    pub fn new() -> Validator {
+        return Box::new(Self {});
+    }
+}
+
+impl ValidatorTrait for GratuitousValidator {
+    fn is_valid_arp(
+        &self,
+        _ether_packet: &EthernetPacket,
+        arp_packet: &ArpPacket,
+    ) -> bool {
+        return arp_packet.get_sender_proto_addr()
+            == arp_packet.get_target_proto_addr();
+    }
+}
+
+pub struct SrcNetValidator {
+    nets: Vec<Ipv4Network>,
+}
+
+impl SrcNetValidator {
+    pub fn new(nets: Vec<Ipv4Network>) -> Validator {
+        return Box::new(Self { nets });
+    }
+}
+
+impl ValidatorTrait for SrcNetValidator {
+    fn is_valid_arp(
+        &self,
+        _ether_packet: &EthernetPacket,
+        arp_packet: &ArpPacket,
+    ) -> bool {
+        let ip = arp_packet.get_sender_proto_addr();
+        return self.nets.iter().any(|net| net.contains(ip));
+    }
+}
+
+pub struct DstNetValidator {
+    nets: Vec<Ipv4Network>,
+}
+
+impl DstNetValidator {
+    pub fn new(nets: Vec<Ipv4Network>) -> Validator {
+        return Box::new(Self { nets });
+    }
+}
+
+impl ValidatorTrait for DstNetValidator {
+    fn is_valid_arp(
+        &self,
+        _ether_packet: &EthernetPacket,
+        arp_packet: &ArpPacket,
+    ) -> bool {
+        let ip = arp_packet.get_target_proto_addr();
+        return self.nets.iter().any(|net| net.contains(ip));
+    }
+}
+
+/// Inclusive range of IPs, from the first to the last.
+pub type IpRange = (Ipv4Addr, Ipv4Addr);
+
+/// Parses a range of IPs in the form first-last, with first <= last.
+pub fn parse_ip_range(v: &str) -> Option<IpRange> {
+    let mut parts = v.splitn(2, '-');
+    let first = parts.next()?.trim().parse::<Ipv4Addr>().ok()?;
+    let last = parts.next()?.trim().parse::<Ipv4Addr>().ok()?;
+    if first > last {
+        return None;
+    }
+    return Some((first, last));
+}
+
+pub struct SrcRangeValidator {
+    ranges: Vec<IpRange>,
+}
+
+impl SrcRangeValidator {
+    pub fn new(ranges: Vec<IpRange>) -> Validator {
+        return Box::new(Self { ranges });
+    }
+}
+
+impl ValidatorTrait for SrcRangeValidator {
+    fn is_valid_arp(
+        &self,
+        _ether_packet: &EthernetPacket,
+        arp_packet: &ArpPacket,
+    ) -> bool {
+        let ip = arp_packet.get_sender_proto_addr();
+        return self
+            .ranges
+            .iter()
+            .any(|(first, last)| *first <= ip && ip <= *last);
+    }
+}
+
+pub struct DstRangeValidator {
+    ranges: Vec<IpRange>,
+}
+
+impl DstRangeValidator {
+    pub fn new(ranges: Vec<IpRange>) -> Validator {
+        return Box::new(Self { ranges });
+    }
+}
+
+impl ValidatorTrait for DstRangeValidator {
+    fn is_valid_arp(
+        &self,
+        _ether_packet: &EthernetPacket,
+        arp_packet: &ArpPacket,
+    ) -> bool {
+        let ip = arp_packet.get_target_proto_addr();
+        return self
+            .ranges
+            .iter()
+            .any(|(first, last)| *first <= ip && ip <= *last);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pnet::packet::{
+        arp::{ArpHardwareTypes, ArpOperations, MutableArpPacket},
+        ethernet::MutableEthernetPacket,
+        MutablePacket,
+    };
+
+    /// Frame of an ARP request, whose sender MAC is also the Ethernet source.
+    pub fn arp_frame(
+        sender: (&str, &str),
+        target: (&str, &str),
+        ether_dst: &str,
+    ) -> Vec<u8> {
+        let mut buf = vec![0u8; 42];
+        let mut ether = MutableEthernetPacket::new(&mut buf).unwrap();
+        ether.set_source(sender.1.parse().unwrap());
+        ether.set_destination(ether_dst.parse().unwrap());
+        ether.set_ethertype(EtherTypes::Arp);
+
+        let mut arp = MutableArpPacket::new(ether.payload_mut()).unwrap();
+        arp.set_hardware_type(ArpHardwareTypes::Ethernet);
+        arp.set_protocol_type(EtherTypes::Ipv4);
+        arp.set_hw_addr_len(6);
+        arp.set_proto_addr_len(4);
+        arp.set_operation(ArpOperations::Request);
+        arp.set_sender_proto_addr(sender.0.parse().unwrap());
+        arp.set_sender_hw_addr(sender.1.parse().unwrap());
+        arp.set_target_proto_addr(target.0.parse().unwrap());
+        arp.set_target_hw_addr(target.1.parse().unwrap());
+        return buf;
+    }
+
+    fn request(sender_ip: &str, target_ip: &str) -> Vec<u8> {
+        return arp_frame(
+            (sender_ip, "52:54:00:00:00:01"),
+            (target_ip, "00:00:00:00:00:00"),
+            "ff:ff:ff:ff:ff:ff",
+        );
+    }
+
+    #[test]
+    fn parses_ip_ranges() {
+        let first = Ipv4Addr::new(10, 0, 0, 1);
+        let last = Ipv4Addr::new(10, 0, 1, 9);
+        assert_eq!(parse_ip_range("10.0.0.1-10.0.1.9"), Some((first, last)));
+        assert_eq!(parse_ip_range("10.0.0.1 - 10.0.1.9"), Some((first, last)));
+        assert_eq!(parse_ip_range("10.0.0.1-10.0.0.1"), Some((first, first)));
+        assert_eq!(parse_ip_range("10.0.1.9-10.0.0.1"), None);
+        assert_eq!(parse_ip_range("10.0.0.1"), None);
+        assert_eq!(parse_ip_range("10.0.0.1-10.0.0.256"), None);
+        assert_eq!(parse_ip_range("10.0.0.0/24-10.0.1.0"), None);
+    }
+
+    #[test]
+    fn matches_networks_and_ranges_without_expanding() {
+        let net = SrcNetValidator::new(vec!["10.0.0.0/8".parse().unwrap()]);
+        assert!(net.is_valid(&request("10.255.0.1", "192.168.0.1")));
+        assert!(!net.is_valid(&request("192.168.0.1", "10.0.0.1")));
+
+        let range =
+            DstRangeValidator::new(vec![
+                parse_ip_range("10.0.0.250-10.0.1.5").unwrap()
+            ]);
+        assert!(range.is_valid(&request("10.0.0.1", "10.0.0.250")));
+        assert!(range.is_valid(&request("10.0.0.1", "10.0.1.5")));
+        assert!(!range.is_valid(&request("10.0.0.1", "10.0.1.6")));
+    }
+}